@@ -6,11 +6,11 @@ struct CharBucket;
 
 impl BucketOption<char> for CharBucket {
     fn size(&self) -> usize {
-        return char::MAX as usize;
+        char::MAX as usize
     }
 
     fn bucket_index(&self, value: &char) -> usize {
-        return (*value) as usize;
+        (*value) as usize
     }
 }
 
@@ -32,13 +32,13 @@ fn main() {
         };
     }
 
-    let mut bucket = SuffixArrayBucket::new(&chars, &types, &CharBucket);
+    let mut bucket = SuffixArrayBucket::<_, _, u32>::new(&chars, &types, &CharBucket);
     for (index, char) in chars.iter().enumerate() {
         bucket.push(index, char, SuffixType::L);
     }
 
     for bin in 0..bucket.num_bins() {
-        if bucket.len_l_bin(bin) + bucket.len_s_bin(bin) <= 0 {
+        if bucket.len_l_bin(bin) + bucket.len_s_bin(bin) == 0 {
             continue;
         }
 
@@ -9,11 +9,11 @@ struct CharBucket;
 
 impl BucketOption<char> for CharBucket {
     fn size(&self) -> usize {
-        return char::MAX as usize;
+        char::MAX as usize
     }
 
     fn bucket_index(&self, value: &char) -> usize {
-        return (*value) as usize;
+        (*value) as usize
     }
 }
 
@@ -22,7 +22,7 @@ fn main() {
     stdin().read_to_string(&mut input).unwrap();
 
     let chars = input.chars().collect::<Vec<_>>();
-    let sa = suffix_array(&chars, &CharBucket);
+    let sa: Vec<u32> = suffix_array(&chars, &CharBucket);
     let bwt = bwt(&chars, &sa);
 
     println!("{}", String::from_iter(bwt));
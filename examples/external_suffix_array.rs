@@ -0,0 +1,41 @@
+use std::{fs::File, path::Path};
+
+use azuki::core::{
+    external_suffix_array::external_suffix_array,
+    suffix_array::{lcp_from_plcp, plcp_array},
+    suffix_reference::back_array,
+};
+use memmap2::Mmap;
+
+// external_suffix_array INPUT SUFFIX_ARRAY [MEMORY]
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let memory = args
+        .get(3)
+        .map_or(1 << 24, |memory| memory.parse().unwrap());
+
+    let input = File::open(&args[1]).unwrap();
+    let sa = external_suffix_array::<u32>(&input, Path::new(&args[2]), memory).unwrap();
+
+    // the input and the suffix array are paged in as the arrays below are built,
+    // the LCP array by way of the PLCP array so that no rank array is needed
+    let data = unsafe { Mmap::map(&input).unwrap() };
+    let plcp: Vec<u32> = plcp_array(&data, &sa);
+    let lcp: Vec<u32> = lcp_from_plcp(&sa, &plcp).collect();
+    drop(plcp);
+    let back = back_array(&sa, &lcp);
+
+    for (rank, index) in sa.iter().map(|&index| index as usize).enumerate() {
+        let stop = data.len().min(index + 8);
+        println!(
+            "{:>8} ({:>8}) [{:>8}]: {:?}{}",
+            index,
+            lcp[rank],
+            back[index]
+                .get()
+                .map_or(Default::default(), |b| format!("{}", b.index)),
+            String::from_utf8_lossy(&data[index..stop]),
+            if stop < data.len() { "..." } else { "" },
+        );
+    }
+}
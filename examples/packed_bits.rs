@@ -22,8 +22,8 @@ fn main() {
         &packed[..packed.len().min(20)]
     );
 
-    let mut unpacked = PackedBits::prepare(&packed);
-    PackedBits::extract(&packed, &mut unpacked);
+    let mut unpacked = PackedBits::prepare(&packed).unwrap();
+    PackedBits::extract(&packed, &mut unpacked).unwrap();
 
     println!(
         "({:x}) {:x?}",
@@ -0,0 +1,27 @@
+use std::io::{copy, stdin, Read};
+
+use azuki::{
+    core::frame::{FreezeOptions, MicrowaveOptions},
+    Decoder, Encoder,
+};
+
+fn main() {
+    let mut input = vec![];
+    stdin().read_to_end(&mut input).unwrap();
+
+    let mut encoder = Encoder::new(vec![], FreezeOptions::default());
+    copy(&mut &input[..], &mut encoder).unwrap();
+    let frozen = encoder.finish().unwrap();
+
+    let mut decoder = Decoder::new(&frozen[..], MicrowaveOptions::default());
+    let mut microwaved = vec![];
+    copy(&mut decoder, &mut microwaved).unwrap();
+
+    println!(
+        "({:x}) -> ({:x}) -> ({:x}) {}",
+        input.len(),
+        frozen.len(),
+        microwaved.len(),
+        if input == microwaved { "ok" } else { "NG" }
+    );
+}
@@ -9,11 +9,11 @@ struct CharBucket;
 
 impl BucketOption<char> for CharBucket {
     fn size(&self) -> usize {
-        return char::MAX as usize;
+        char::MAX as usize
     }
 
     fn bucket_index(&self, value: &char) -> usize {
-        return (*value) as usize;
+        (*value) as usize
     }
 }
 
@@ -22,7 +22,7 @@ fn main() {
     stdin().read_to_string(&mut input).unwrap();
 
     let chars = input.chars().collect::<Vec<_>>();
-    let sa = suffix_array(&chars, &CharBucket);
+    let sa: Vec<u32> = suffix_array(&chars, &CharBucket);
     let rank = rank_array(&sa);
     let lcp = lcp_array(&chars, &sa, &rank);
     let back = back_array(&sa, &lcp);
@@ -38,28 +38,27 @@ fn main() {
         };
     }
 
-    for (rank, &index) in sa.iter().enumerate() {
+    for (rank, index) in sa.iter().map(|&index| index as usize).enumerate() {
+        let back = back[index].get();
         let stop = index + 8;
         let stop = chars.len().min(stop);
         println!(
             "{:>8} ({:>8}) [{}{:>7} ({}{:>7})]: {}{}",
             index,
             lcp[rank],
-            back[index].map_or(Default::default(), |b| format!(
-                "{}",
-                if index <= b.index { "!" } else { " " }
-            )),
-            back[index].map_or(Default::default(), |b| format!("{}", b.index)),
-            back[index].map_or(Default::default(), |b| format!(
-                "{}",
-                if lcp[rank] < b.lcp && lcp.get(rank + 1).map_or(false, |l| *l < b.lcp) {
+            back.map_or("", |b| if index <= b.index { "!" } else { " " }),
+            back.map_or(Default::default(), |b| format!("{}", b.index)),
+            back.map_or("", |b| {
+                if (lcp[rank] as usize) < b.lcp
+                    && lcp.get(rank + 1).is_some_and(|&l| (l as usize) < b.lcp)
+                {
                     "!"
                 } else {
                     " "
                 }
-            )),
-            back[index].map_or(Default::default(), |b| format!("{}", b.lcp)),
-            String::from_iter(chars[index..stop].into_iter()),
+            }),
+            back.map_or(Default::default(), |b| format!("{}", b.lcp)),
+            String::from_iter(chars[index..stop].iter()),
             if stop < chars.len() { "..." } else { "" },
         );
     }
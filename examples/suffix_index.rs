@@ -0,0 +1,28 @@
+use std::{fs::File, path::Path};
+
+use azuki::core::{suffix_array::U8Bucket, suffix_reference::SuffixReference};
+use memmap2::Mmap;
+
+// suffix_index DATA INDEX
+// Maps the index of DATA saved at INDEX, building and saving it first when missing or stale,
+// then prints the longest earlier occurrence of every suffix.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let data = unsafe { Mmap::map(&File::open(&args[1]).unwrap()).unwrap() };
+    let path = Path::new(&args[2]);
+
+    let suffix = SuffixReference::<_, u32>::load(&data, path).unwrap_or_else(|error| {
+        eprintln!("rebuilding index: {}", error);
+
+        let suffix = SuffixReference::from_data(&data, &U8Bucket);
+        suffix.save(&data, path).unwrap();
+        suffix
+    });
+
+    for index in 0..data.len() {
+        match suffix.back(index) {
+            Some(back) => println!("{:>8} [{:>8} ({:>8})]", index, back.index, back.lcp),
+            None => println!("{:>8} [-]", index),
+        }
+    }
+}
@@ -6,11 +6,11 @@ struct CharBucket;
 
 impl BucketOption<char> for CharBucket {
     fn size(&self) -> usize {
-        return char::MAX as usize;
+        char::MAX as usize
     }
 
     fn bucket_index(&self, value: &char) -> usize {
-        return (*value) as usize;
+        (*value) as usize
     }
 }
 
@@ -20,7 +20,7 @@ fn main() {
 
     let chars = input.chars().collect::<Vec<_>>();
 
-    let suffix = SuffixReference::from_data(&chars[..], &CharBucket);
+    let suffix = SuffixReference::<_, u32>::from_data(&chars[..], &CharBucket);
 
     for rank in 0..chars.len() {
         let index = suffix.index(rank);
@@ -31,7 +31,7 @@ fn main() {
                 "[{:>8}] {}{}",
                 back.index,
                 &String::from_iter(
-                    chars[back.index..chars.len().min(back.index + back.lcp + 1)].into_iter()
+                    chars[back.index..chars.len().min(back.index + back.lcp + 1)].iter()
                 ),
                 if back.index + back.lcp + 1 < chars.len() {
                     "..."
@@ -43,7 +43,7 @@ fn main() {
             println!(
                 "[{:>8}] {}{}",
                 index,
-                &String::from_iter(chars[index..chars.len().min(index + back.lcp + 1)].into_iter()),
+                &String::from_iter(chars[index..chars.len().min(index + back.lcp + 1)].iter()),
                 if index + back.lcp + 1 < chars.len() {
                     "..."
                 } else {
@@ -58,7 +58,7 @@ fn main() {
             println!(
                 "[{:>8}] {}{}",
                 index,
-                &String::from_iter(chars[index..chars.len().min(index + 1)].into_iter()),
+                &String::from_iter(chars[index..chars.len().min(index + 1)].iter()),
                 if index + 1 < chars.len() { "..." } else { "" },
             );
         }
@@ -1,5 +1,5 @@
 pub trait Command {
     type Args;
 
-    fn execute(&self, args: &Self::Args) -> std::io::Result<()>;
+    fn execute(&self, args: &Self::Args) -> azuki::Result<()>;
 }
@@ -1,6 +1,12 @@
 use clap::Args;
 
-use azuki::core::lz77::LZ77;
+use std::io::{Read, Write};
+
+use azuki::core::{
+    filter,
+    frame::{BlockHeaderLayout, HeaderLayout},
+    lz77::LZ77,
+};
 
 use crate::commands::{
     io::{with_extension, Reading, Writing},
@@ -21,7 +27,7 @@ pub struct DumpCommandArgs {
 impl Command for DumpCommand {
     type Args = DumpCommandArgs;
 
-    fn execute(&self, args: &Self::Args) -> std::io::Result<()> {
+    fn execute(&self, args: &Self::Args) -> azuki::Result<()> {
         let input_path = args.input.clone();
         let output_path = args.output.clone();
         let output_path = output_path.or(with_extension(input_path.as_deref(), "dump"));
@@ -29,7 +35,89 @@ impl Command for DumpCommand {
         let mut reading = Reading::open(input_path.as_deref())?;
         let mut writing = Writing::create(output_path.as_deref())?;
 
-        LZ77::dump(&reading.read_data()?, &mut writing)?;
+        let buffer = reading.read_data()?;
+
+        if !HeaderLayout::check(&buffer) {
+            LZ77::dump(&buffer, &mut writing)?;
+            return Ok(());
+        }
+
+        let mut reader = &buffer[..];
+
+        let mut header = HeaderLayout::prepare();
+        HeaderLayout::read(&mut reader, &mut header)?;
+
+        writeln!(
+            writing,
+            "version: {}, codec: {:?}, checksum: {:?}, filters: {:?}, block size: {:x}, window: {}, linked: {}, original size: {}",
+            header.version,
+            header.codec,
+            header.checksum,
+            header.filters,
+            header.block_size,
+            header
+                .window
+                .map_or("-".to_string(), |window| format!("{:x}", window)),
+            header.linked,
+            header
+                .original_size
+                .map_or("-".to_string(), |size| format!("{:x}", size))
+        )?;
+
+        // blocks of linked streams are extracted as well, as the ones after reach back into them
+        let mut history = vec![];
+
+        loop {
+            let mut block_header = BlockHeaderLayout::prepare();
+            BlockHeaderLayout::read(&mut reader, &mut block_header, header.codec)?;
+
+            if block_header.original_size == 0 {
+                let mut digest = vec![0u8; header.checksum.size()];
+                reader.read_exact(&mut digest)?;
+                writeln!(writing, "stream checksum: {:x?}", digest)?;
+                break;
+            }
+
+            if block_header.original_size > header.block_size {
+                return Err(azuki::Error::BlockTooLarge {
+                    size: block_header.original_size,
+                    limit: header.block_size,
+                });
+            }
+
+            let codec = block_header.codec.unwrap_or(header.codec);
+            writeln!(
+                writing,
+                "block: original size: {:x}, compressed size: {:x}, codec: {:?}",
+                block_header.original_size, block_header.compressed_size, codec
+            )?;
+
+            // grow the buffer only as far as the input actually reaches
+            let mut compressed = vec![];
+            (&mut reader)
+                .take(block_header.compressed_size as u64)
+                .read_to_end(&mut compressed)?;
+            if compressed.len() != block_header.compressed_size {
+                return Err(azuki::Error::Truncated);
+            }
+            // the codec sees the block as the filters left it
+            let max_size = filter::encoded_size(&header.filters, block_header.original_size);
+            codec.dump(&compressed, &history, max_size, &mut writing)?;
+
+            let history_size = header.history_size();
+            if history_size > 0 {
+                let window = header.window.unwrap_or(usize::MAX);
+                let mut filtered = vec![];
+                codec.extract(&compressed, &history, max_size, window, &mut filtered)?;
+
+                history.extend_from_slice(&filtered);
+                history.drain(..history.len().saturating_sub(history_size));
+            }
+
+            let mut digest = vec![0u8; header.checksum.size()];
+            reader.read_exact(&mut digest)?;
+            writeln!(writing, "block checksum: {:x?}", digest)?;
+        }
 
         Ok(())
     }
@@ -1,9 +1,15 @@
 use clap::Args;
 
-use azuki::core::lz77::LZ77;
+use azuki::core::{
+    checksum::Checksum,
+    filter::FilterStage,
+    frame::{Codec, Frame, FreezeOptions, DEFAULT_BLOCK_SIZE},
+    lz77::CompressOptions,
+};
 
 use crate::commands::{
     io::{with_extension, Reading, Writing},
+    size::parse_size,
     Command,
 };
 
@@ -16,12 +22,52 @@ pub struct FreezeCommandArgs {
 
     #[arg(short, long)]
     pub output: Option<String>,
+
+    /// Codec compressing every block
+    /// (`lz77`, `lz77-huffman`, `lz77-range`, `bwt`, `lzss[:c2l13|c3l12|c4l11|l7c8]`, `stored` or `auto`)
+    #[arg(short, long, default_value = "lz77")]
+    pub codec: Codec,
+
+    /// Filters applied in order before compression
+    /// (`delta:STRIDE`, `shuffle:WIDTH`, `bcj` or `split:BITS`, separated by commas)
+    #[arg(short, long, value_delimiter = ',')]
+    pub filter: Vec<FilterStage>,
+
+    /// Size of independently compressed blocks (accepts K, M and G suffixes)
+    #[arg(short, long, value_parser = parse_size, default_value_t = DEFAULT_BLOCK_SIZE)]
+    pub block_size: usize,
+
+    /// Farthest distance a match may reach back, into earlier blocks too
+    /// (accepts K, M and G suffixes, default unbounded within a block)
+    #[arg(short, long, value_parser = parse_size)]
+    pub window: Option<usize>,
+
+    /// Compression level from 1 (fastest) to 9 (smallest)
+    #[arg(
+        short,
+        long,
+        value_parser = clap::value_parser!(u8).range(1..=9),
+        default_value_t = CompressOptions::DEFAULT_LEVEL
+    )]
+    pub level: u8,
+
+    /// Same as `--level 9`
+    #[arg(long, conflicts_with = "level")]
+    pub best: bool,
+
+    /// Checksum stored per block and for the whole stream (`none` or `crc32`)
+    #[arg(long, default_value = "crc32")]
+    pub checksum: Checksum,
+
+    /// Blocks compressed in parallel, each held in memory along with its output
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: u16,
 }
 
 impl Command for FreezeCommand {
     type Args = FreezeCommandArgs;
 
-    fn execute(&self, args: &Self::Args) -> std::io::Result<()> {
+    fn execute(&self, args: &Self::Args) -> azuki::Result<()> {
         let input_path = args.input.clone();
         let output_path = args.output.clone();
         let output_path = output_path.or(with_extension(input_path.as_deref(), "frozen"));
@@ -29,7 +75,24 @@ impl Command for FreezeCommand {
         let mut reading = Reading::open(input_path.as_deref())?;
         let mut writing = Writing::create(output_path.as_deref())?;
 
-        LZ77::compress(&reading.read_data()?, &mut writing)?;
+        let options = FreezeOptions {
+            codec: args.codec,
+            filters: args.filter.clone(),
+            block_size: args.block_size,
+            compress: CompressOptions {
+                window: args.window,
+                ..CompressOptions::with_level(if args.best {
+                    CompressOptions::MAX_LEVEL
+                } else {
+                    args.level
+                })
+            },
+            checksum: args.checksum,
+            original_size: reading.size(),
+            threads: args.threads as usize,
+        };
+
+        Frame::freeze(&mut reading, &mut writing, &options)?;
 
         Ok(())
     }
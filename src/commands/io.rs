@@ -6,7 +6,7 @@ use std::{
 };
 
 pub fn with_extension(path: Option<&str>, extension: &str) -> Option<String> {
-    let Some(path) = path else { return None };
+    let path = path?;
 
     let path = Path::new(&path);
 
@@ -24,7 +24,7 @@ pub fn with_extension(path: Option<&str>, extension: &str) -> Option<String> {
 }
 
 pub fn without_extension(path: Option<&str>, extension: &str) -> Option<String> {
-    let Some(path) = path else { return None };
+    let path = path?;
 
     let path = Path::new(&path);
     let extension = OsStr::new(extension);
@@ -60,6 +60,18 @@ impl Reading {
         }
     }
 
+    pub fn size(&self) -> Option<usize> {
+        match self {
+            Self::BufReader(reader) => reader
+                .get_ref()
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())
+                .map(|metadata| metadata.len() as usize),
+            Self::Stdin(_) => None,
+        }
+    }
+
     pub fn read_data(&mut self) -> std::io::Result<Vec<u8>> {
         let mut buffer = vec![];
         self.read_to_end(&mut buffer)?;
@@ -1,13 +1,19 @@
 use clap::Args;
 
-use azuki::core::lz77::LZ77;
+use azuki::core::{
+    frame::{Frame, MicrowaveOptions},
+    lz77_stream::LZ77Decoder,
+};
 
 use crate::commands::{
     io::{Reading, Writing},
     Command,
 };
 
-use super::io::{with_extension, without_extension};
+use super::{
+    io::{with_extension, without_extension},
+    size::parse_size,
+};
 
 pub struct MicrowaveCommand;
 
@@ -18,12 +24,29 @@ pub struct MicrowaveCommandArgs {
 
     #[arg(short, long)]
     pub output: Option<String>,
+
+    /// Extract a headerless stream written by older versions
+    #[arg(long)]
+    pub legacy: bool,
+
+    /// Farthest distance a match reaches back, bounding the memory LZ77 blocks are extracted in
+    /// (accepts K, M and G suffixes, default the window in the header, if any)
+    #[arg(short, long, value_parser = parse_size)]
+    pub window: Option<usize>,
+
+    /// Skip checksum verification
+    #[arg(long)]
+    pub no_check: bool,
+
+    /// Blocks extracted in parallel unless frozen with a window, each held in memory along with its input
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub threads: u16,
 }
 
 impl Command for MicrowaveCommand {
     type Args = MicrowaveCommandArgs;
 
-    fn execute(&self, args: &Self::Args) -> std::io::Result<()> {
+    fn execute(&self, args: &Self::Args) -> azuki::Result<()> {
         let input_path = args.input.clone();
         let output_path = args.output.clone();
         let output_path = output_path.or(with_extension(
@@ -34,7 +57,18 @@ impl Command for MicrowaveCommand {
         let mut reading = Reading::open(input_path.as_deref())?;
         let mut writing = Writing::create(output_path.as_deref())?;
 
-        LZ77::extract(&reading.read_data()?, &mut writing)?;
+        if args.legacy {
+            let window = args.window.unwrap_or(usize::MAX);
+            std::io::copy(&mut LZ77Decoder::new(reading, window), &mut writing)?;
+        } else {
+            let options = MicrowaveOptions {
+                verify: !args.no_check,
+                threads: args.threads as usize,
+                window: args.window,
+            };
+
+            Frame::microwave(&mut reading, &mut writing, &options)?;
+        }
 
         Ok(())
     }
@@ -3,6 +3,7 @@ mod dump;
 mod freeze;
 mod io;
 mod microwave;
+mod size;
 
 pub use command::Command;
 pub use dump::{DumpCommand, DumpCommandArgs};
@@ -0,0 +1,18 @@
+pub fn parse_size(value: &str) -> Result<usize, String> {
+    let value = value.trim();
+    let (number, shift) = match value.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&value[..index], 10),
+        Some((index, 'm' | 'M')) => (&value[..index], 20),
+        Some((index, 'g' | 'G')) => (&value[..index], 30),
+        _ => (value, 0),
+    };
+
+    let number: usize = number
+        .parse()
+        .map_err(|_| format!("invalid size `{}`", value))?;
+
+    number
+        .checked_mul(1 << shift)
+        .filter(|&size| size > 0)
+        .ok_or_else(|| format!("size `{}` out of range", value))
+}
@@ -0,0 +1,166 @@
+use crate::error::{Error, Result};
+
+// Bits are packed from the least significant bit of each byte onwards.
+pub struct BitWriter {
+    buffer: Vec<u8>,
+    acc: u64,
+    acc_bits: u32,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self {
+            buffer: vec![],
+            acc: 0,
+            acc_bits: 0,
+        }
+    }
+
+    // Writes the lowest `bits` bits of `value`.
+    pub fn write(&mut self, value: u64, bits: u32) {
+        if bits > 32 {
+            self.write(value, 32);
+            self.write(value >> 32, bits - 32);
+            return;
+        }
+
+        self.acc |= (value & ((1u64 << bits) - 1)) << self.acc_bits;
+        self.acc_bits += bits;
+
+        while self.acc_bits >= 8 {
+            self.buffer.push(self.acc as u8);
+            self.acc >>= 8;
+            self.acc_bits -= 8;
+        }
+    }
+
+    // Writes a code whose first bit is its most significant one, as Huffman codes are read.
+    pub fn write_code(&mut self, code: u32, bits: u32) {
+        let reversed = code
+            .reverse_bits()
+            .checked_shr(u32::BITS - bits)
+            .unwrap_or(0);
+        self.write(reversed as u64, bits);
+    }
+
+    // Pads the last byte with zero bits.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.acc_bits > 0 {
+            self.buffer.push(self.acc as u8);
+        }
+
+        self.buffer
+    }
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct BitReader<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+    acc: u64,
+    acc_bits: u32,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            cursor: 0,
+            acc: 0,
+            acc_bits: 0,
+        }
+    }
+
+    pub fn read(&mut self, bits: u32) -> Result<u64> {
+        if bits > 32 {
+            let low = self.read(32)?;
+            return Ok(low | (self.read(bits - 32)? << 32));
+        }
+
+        while self.acc_bits < bits {
+            let Some(&byte) = self.buffer.get(self.cursor) else {
+                return Err(Error::Truncated);
+            };
+
+            self.acc |= (byte as u64) << self.acc_bits;
+            self.acc_bits += 8;
+            self.cursor += 1;
+        }
+
+        let value = self.acc & ((1u64 << bits) - 1);
+        self.acc >>= bits;
+        self.acc_bits -= bits;
+
+        Ok(value)
+    }
+
+    pub fn read_bit(&mut self) -> Result<u32> {
+        Ok(self.read(1)? as u32)
+    }
+
+    // Whole bytes consumed so far, including a partially read one.
+    pub fn position(&self) -> usize {
+        self.cursor - (self.acc_bits / 8) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    #[test]
+    fn random_roundtrips() {
+        let mut random = Random(1);
+        for _ in 0..100 {
+            let fields: Vec<(u64, u32)> = (0..random.next() % 200)
+                .map(|_| (random.next(), (random.next() % 65) as u32))
+                .collect();
+
+            let mut writer = BitWriter::new();
+            for &(value, bits) in fields.iter() {
+                writer.write(value, bits);
+            }
+            let buffer = writer.finish();
+            let total_bits: u32 = fields.iter().map(|&(_, bits)| bits).sum();
+            assert_eq!(buffer.len(), total_bits.div_ceil(8) as usize);
+
+            let mut reader = BitReader::new(&buffer);
+            for &(value, bits) in fields.iter() {
+                let mask = u64::MAX.checked_shr(64 - bits).unwrap_or(0);
+                assert_eq!(reader.read(bits).unwrap(), value & mask);
+            }
+            assert_eq!(reader.position(), buffer.len());
+        }
+    }
+
+    #[test]
+    fn codes_are_read_most_significant_bit_first() {
+        let mut writer = BitWriter::new();
+        writer.write_code(0b110, 3);
+        writer.write_code(0b0111_0000_0000_0001, 15);
+        writer.write_code(0, 0);
+        let buffer = writer.finish();
+
+        let mut reader = BitReader::new(&buffer);
+        let bits: Vec<u32> = (0..18).map(|_| reader.read_bit().unwrap()).collect();
+        assert_eq!(bits, [1, 1, 0, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn reads_past_the_end_are_truncated() {
+        let mut reader = BitReader::new(&[0xff, 0x01]);
+        assert_eq!(reader.read(9).unwrap(), 0x1ff);
+        assert_eq!(reader.read(7).unwrap(), 0);
+        assert!(matches!(reader.read(1), Err(Error::Truncated)));
+        assert!(matches!(
+            BitReader::new(&[]).read_bit(),
+            Err(Error::Truncated)
+        ));
+    }
+}
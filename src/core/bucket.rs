@@ -0,0 +1,32 @@
+// Splits token values into a bucket symbol and extra bits telling the values within apart.
+// Values below `DIRECT_BUCKETS` are their own bucket, larger ones share one per half power of two.
+pub const DIRECT_BUCKETS: usize = 4;
+pub const BUCKET_COUNT: usize = 2 * usize::BITS as usize;
+
+pub fn split(value: usize) -> (usize, u32, u64) {
+    if value < DIRECT_BUCKETS {
+        return (value, 0, 0);
+    }
+
+    let bits = usize::BITS - 1 - value.leading_zeros();
+    let extra_bits = bits - 1;
+    let symbol = 2 * bits as usize + ((value >> extra_bits) & 1);
+
+    (symbol, extra_bits, (value & ((1 << extra_bits) - 1)) as u64)
+}
+
+pub fn extra_bits(symbol: usize) -> u32 {
+    if symbol < DIRECT_BUCKETS {
+        0
+    } else {
+        (symbol / 2) as u32 - 1
+    }
+}
+
+pub fn join(symbol: usize, extra: u64) -> usize {
+    if symbol < DIRECT_BUCKETS {
+        return symbol;
+    }
+
+    ((2 | (symbol & 1)) << extra_bits(symbol)) | extra as usize
+}
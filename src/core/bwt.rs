@@ -1,4 +1,17 @@
-pub fn bwt<T: Clone>(data: &[T], suffix_array: &[usize]) -> Vec<T> {
+use std::io::Write;
+
+use crate::error::{Error, Result};
+
+use super::{
+    bits::{BitReader, BitWriter},
+    head::{Common, Head},
+    huffman::{HuffmanDecoder, HuffmanTable},
+    suffix_array::{suffix_array, SuffixIndex, U8Bucket},
+};
+
+// Transform of `data` followed by a unique smallest sentinel, with the sentinel left out.
+// The first byte comes from the row starting with the sentinel, the rest follow `suffix_array`.
+pub fn bwt<T: Clone, I: SuffixIndex>(data: &[T], suffix_array: &[I]) -> Vec<T> {
     let mut bwt = vec![];
 
     if let Some(last) = data.last() {
@@ -6,7 +19,8 @@ pub fn bwt<T: Clone>(data: &[T], suffix_array: &[usize]) -> Vec<T> {
     }
 
     for &index in suffix_array.iter() {
-        if index <= 0 {
+        let index = index.to_usize();
+        if index == 0 {
             continue;
         }
 
@@ -15,3 +29,325 @@ pub fn bwt<T: Clone>(data: &[T], suffix_array: &[usize]) -> Vec<T> {
 
     bwt
 }
+
+// Row of the sorted rotations whose last byte is the sentinel `bwt` leaves out.
+// Row 0 starts with the sentinel, so this is always between 1 and `data.len()`.
+pub fn primary_index<I: SuffixIndex>(suffix_array: &[I]) -> usize {
+    suffix_array
+        .iter()
+        .position(|&index| index.to_usize() == 0)
+        .map_or(0, |rank| rank + 1)
+}
+
+fn check_primary_index(primary_index: usize, size: usize) -> Result<()> {
+    if size > 0 && (primary_index == 0 || primary_index > size) {
+        return Err(Error::InvalidPrimaryIndex {
+            index: primary_index,
+            size,
+        });
+    }
+
+    Ok(())
+}
+
+pub fn inverse_bwt(bwt: &[u8], primary_index: usize) -> Result<Vec<u8>> {
+    if bwt.is_empty() {
+        return Ok(vec![]);
+    }
+
+    check_primary_index(primary_index, bwt.len())?;
+
+    // rows before the primary index hold `bwt` as is, the ones after it are shifted by one
+    let row_byte = |row: usize| bwt[if row < primary_index { row } else { row - 1 }];
+
+    let mut starts = [0usize; 0x100];
+    for &byte in bwt.iter() {
+        starts[byte as usize] += 1;
+    }
+    let mut start = 1;
+    for count in starts.iter_mut() {
+        (*count, start) = (start, start + *count);
+    }
+
+    // the row each row turns into when its last byte is rotated to the front
+    let mut lf = vec![0usize; bwt.len() + 1];
+    for (row, next) in lf.iter_mut().enumerate() {
+        if row != primary_index {
+            let start = &mut starts[row_byte(row) as usize];
+            *next = *start;
+            *start += 1;
+        }
+    }
+
+    let mut data = vec![0u8; bwt.len()];
+    let mut row = 0;
+    for byte in data.iter_mut().rev() {
+        *byte = row_byte(row);
+        row = lf[row];
+    }
+
+    Ok(data)
+}
+
+// Zero runs of the move-to-front output are written in bijective base 2 with two symbols,
+// other values shifted past them, as in bzip2.
+const RUN_A: usize = 0;
+const RUN_B: usize = 1;
+const END_SYMBOL: usize = 0x100 + 1;
+const SYMBOL_COUNT: usize = END_SYMBOL + 1;
+
+// Burrows-Wheeler transform, move-to-front, zero run length and Huffman coding as in bzip2,
+// with a single table per block. Every block starts with its primary index as a `Head<Common>`.
+pub struct BWT;
+
+impl BWT {
+    pub fn compress(data: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
+        let (transformed, primary_index) = if data.len() < u32::MAX as usize {
+            Self::transform::<u32>(data)
+        } else {
+            Self::transform::<u64>(data)
+        };
+
+        let mut symbols = vec![];
+        let mut run = 0;
+        let mut order = MoveToFront::new();
+
+        for &byte in transformed.iter() {
+            let value = order.encode(byte);
+
+            if value == 0 {
+                run += 1;
+                continue;
+            }
+
+            push_run(&mut symbols, run);
+            run = 0;
+            symbols.push(value as usize + 1);
+        }
+
+        push_run(&mut symbols, run);
+        symbols.push(END_SYMBOL);
+
+        let mut frequencies = vec![0usize; SYMBOL_COUNT];
+        for &symbol in symbols.iter() {
+            frequencies[symbol] += 1;
+        }
+
+        let table = HuffmanTable::from_frequencies(&frequencies);
+        let mut bits = BitWriter::new();
+        table.write_lengths(&mut bits);
+
+        for &symbol in symbols.iter() {
+            table.write_symbol(symbol, &mut bits);
+        }
+
+        Head::<Common>::write(&primary_index, writer)?;
+        writer.write_all(&bits.finish())
+    }
+
+    fn transform<I: SuffixIndex>(data: &[u8]) -> (Vec<u8>, usize) {
+        let suffix_array: Vec<I> = suffix_array(data, &U8Bucket);
+        (bwt(data, &suffix_array), primary_index(&suffix_array))
+    }
+
+    pub fn extract(buffer: &[u8], max_size: usize, writer: &mut impl Write) -> Result<usize> {
+        let mut primary_index = 0;
+        let cursor = Head::<Common>::extract(buffer, &mut primary_index)?;
+
+        let transformed = Self::decode(&buffer[cursor..], max_size)?;
+        let data = inverse_bwt(&transformed, primary_index)?;
+
+        writer.write_all(&data)?;
+        Ok(data.len())
+    }
+
+    pub fn dump(buffer: &[u8], max_size: usize, writer: &mut impl Write) -> Result<()> {
+        let mut primary_index = 0;
+        let cursor = Head::<Common>::extract(buffer, &mut primary_index)?;
+
+        let transformed = Self::decode(&buffer[cursor..], max_size)?;
+        check_primary_index(primary_index, transformed.len())?;
+        writeln!(
+            writer,
+            "{:>8x}: primary index: {:x}, size: {:x}",
+            0,
+            primary_index,
+            transformed.len()
+        )?;
+
+        Ok(())
+    }
+
+    // Transformed bytes back from the Huffman coded move-to-front values.
+    fn decode(buffer: &[u8], max_size: usize) -> Result<Vec<u8>> {
+        let mut bits = BitReader::new(buffer);
+        let decoder = HuffmanDecoder::read_lengths(&mut bits, SYMBOL_COUNT)?;
+
+        let mut transformed = vec![];
+        let mut order = MoveToFront::new();
+        let mut run = 0usize;
+        let mut weight = 1usize;
+
+        loop {
+            let symbol = decoder.read_symbol(&mut bits)?;
+
+            if symbol == RUN_A || symbol == RUN_B {
+                run = run.saturating_add(weight.saturating_mul(symbol + 1));
+                weight = weight.saturating_mul(2);
+
+                if run > max_size - transformed.len() {
+                    return Err(Error::SizeMismatch {
+                        expected: max_size,
+                        actual: transformed.len().saturating_add(run),
+                    });
+                }
+                continue;
+            }
+
+            transformed.resize(transformed.len() + run, order.decode(0));
+            run = 0;
+            weight = 1;
+
+            if symbol == END_SYMBOL {
+                break;
+            }
+
+            if transformed.len() >= max_size {
+                return Err(Error::SizeMismatch {
+                    expected: max_size,
+                    actual: transformed.len() + 1,
+                });
+            }
+
+            transformed.push(order.decode((symbol - 1) as u8));
+        }
+
+        Ok(transformed)
+    }
+}
+
+// Writes a zero run of `run` bytes with digits of 1 (`RUN_A`) and 2 (`RUN_B`).
+fn push_run(symbols: &mut Vec<usize>, mut run: usize) {
+    while run > 0 {
+        if run & 1 == 1 {
+            symbols.push(RUN_A);
+            run = (run - 1) / 2;
+        } else {
+            symbols.push(RUN_B);
+            run = (run - 2) / 2;
+        }
+    }
+}
+
+struct MoveToFront {
+    order: [u8; 0x100],
+}
+
+impl MoveToFront {
+    fn new() -> Self {
+        Self {
+            order: std::array::from_fn(|index| index as u8),
+        }
+    }
+
+    fn encode(&mut self, byte: u8) -> u8 {
+        let position = self.order.iter().position(|&other| other == byte).unwrap();
+        self.order.copy_within(0..position, 1);
+        self.order[0] = byte;
+        position as u8
+    }
+
+    fn decode(&mut self, position: u8) -> u8 {
+        let position = position as usize;
+        let byte = self.order[position];
+        self.order.copy_within(0..position, 1);
+        self.order[0] = byte;
+        byte
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::{fibonacci, runs, Random};
+
+    const MAX_SIZE: usize = 1 << 16;
+
+    fn roundtrip(data: &[u8]) {
+        let mut compressed = vec![];
+        BWT::compress(data, &mut compressed).unwrap();
+
+        let mut extracted = vec![];
+        BWT::extract(&compressed, data.len(), &mut extracted).unwrap();
+        assert_eq!(extracted, data);
+        BWT::dump(&compressed, data.len(), &mut std::io::sink()).unwrap();
+    }
+
+    // A block of the given primary index and symbols, coded with a table fitting them.
+    fn crafted(primary_index: usize, symbols: &[usize]) -> Vec<u8> {
+        let mut frequencies = vec![0usize; SYMBOL_COUNT];
+        for &symbol in symbols.iter() {
+            frequencies[symbol] += 1;
+        }
+        let table = HuffmanTable::from_frequencies(&frequencies);
+
+        let mut bits = BitWriter::new();
+        table.write_lengths(&mut bits);
+        for &symbol in symbols.iter() {
+            table.write_symbol(symbol, &mut bits);
+        }
+
+        let mut block = vec![];
+        Head::<Common>::write(&primary_index, &mut block).unwrap();
+        block.extend(bits.finish());
+        block
+    }
+
+    fn assert_rejected(block: &[u8]) {
+        assert!(BWT::dump(block, MAX_SIZE, &mut std::io::sink()).is_err());
+        assert!(BWT::extract(block, MAX_SIZE, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn roundtrips() {
+        roundtrip(&[]);
+        roundtrip(&[42]);
+        for data in runs() {
+            roundtrip(&data);
+        }
+        for data in fibonacci(100000) {
+            roundtrip(&data);
+        }
+
+        let mut random = Random(1);
+        for _ in 0..200 {
+            roundtrip(&random.bytes(3000));
+        }
+    }
+
+    #[test]
+    fn rejects_overflowing_runs() {
+        for count in [17, 34, 70, 200] {
+            let mut symbols = vec![2];
+            symbols.extend(std::iter::repeat_n(RUN_B, count));
+            symbols.push(END_SYMBOL);
+            assert_rejected(&crafted(1, &symbols));
+
+            let mut symbols = vec![RUN_A; count];
+            symbols.push(END_SYMBOL);
+            assert_rejected(&crafted(1, &symbols));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_primary_indices() {
+        let symbols = [2, 3, RUN_A, 4, END_SYMBOL];
+        for primary_index in [0, 5, 6, 1 << 40] {
+            assert_rejected(&crafted(primary_index, &symbols));
+        }
+        for primary_index in 1..=4 {
+            let block = crafted(primary_index, &symbols);
+            assert!(BWT::extract(&block, MAX_SIZE, &mut vec![]).is_ok());
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Checksum {
+    None,
+    Crc32,
+}
+
+impl Checksum {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Crc32 => 1,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::None),
+            1 => Some(Self::Crc32),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::Crc32 => 4,
+        }
+    }
+
+    pub fn hasher(&self) -> ChecksumHasher {
+        match self {
+            Self::None => ChecksumHasher::None,
+            Self::Crc32 => ChecksumHasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+impl FromStr for Checksum {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "none" => Ok(Self::None),
+            "crc32" => Ok(Self::Crc32),
+            _ => Err(format!("unknown checksum `{}`", value)),
+        }
+    }
+}
+
+pub enum ChecksumHasher {
+    None,
+    Crc32(crc32fast::Hasher),
+}
+
+impl ChecksumHasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::None => {}
+            Self::Crc32(hasher) => hasher.update(data),
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Self::None => vec![],
+            Self::Crc32(hasher) => hasher.finalize().to_le_bytes().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,601 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, VecDeque},
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    mem::size_of,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use memmap2::Mmap;
+use tempfile::{NamedTempFile, TempPath};
+
+use crate::error::{Error, Result};
+
+use super::suffix_array::SuffixIndex;
+
+// Spill files are read and written through buffers of this size.
+const BUFFER_SIZE: usize = 1 << 16;
+
+// Runs merged at once at most, keeping open files well below common descriptor limits.
+const MAX_FAN_IN: usize = 128;
+
+// Items ranked together before doubling, each taking 9 bits of a `u64` rank.
+const PACKED_ITEMS: usize = 7;
+
+// Suffix array stored on disk as indices in native byte order, mapped into memory so that
+// reading it pages it in instead of holding it. Building the LCP and back arrays from it still
+// takes the data and arrays of its length in memory.
+pub struct SuffixArrayFile<I: SuffixIndex> {
+    map: Mmap,
+    phantom: PhantomData<I>,
+}
+
+impl<I: SuffixIndex> SuffixArrayFile<I> {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        let size = file.metadata()?.len() as usize;
+
+        if !size.is_multiple_of(I::SIZE) {
+            return Err(Error::InvalidIndexFile {
+                size,
+                index_size: I::SIZE,
+            });
+        }
+
+        Ok(Self {
+            map: unsafe { Mmap::map(&file)? },
+            phantom: PhantomData,
+        })
+    }
+}
+
+impl<I: SuffixIndex> Deref for SuffixArrayFile<I> {
+    type Target = [I];
+
+    fn deref(&self) -> &[I] {
+        // mappings are page aligned, and `SuffixIndex` is only implemented for integers,
+        // valid for any bytes of their size
+        unsafe {
+            std::slice::from_raw_parts(self.map.as_ptr().cast::<I>(), self.map.len() / I::SIZE)
+        }
+    }
+}
+
+// Builds the suffix array of everything `reader` yields into the file at `path`, keeping about
+// `memory` bytes in memory, and spilling the rest to temporary files next to `path`.
+// Suffixes are sorted by prefix doubling with discarding: each round sorts the suffixes still
+// sharing their first `h` items with others by the rank of the `h` items after, and suffixes
+// left alone keep their rank and drop out of later rounds. A round scans the ranks of all
+// suffixes, but only sorts the ones left, so its cost follows how repetitive the input is.
+// Every step is a sequential pass or an external merge sort of fixed-size records, so the input
+// may exceed memory.
+// Fails before sorting anything when the input has `I::MAX` items or more.
+pub fn external_suffix_array<I: SuffixIndex>(
+    reader: impl Read,
+    path: &Path,
+    memory: usize,
+) -> Result<SuffixArrayFile<I>> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    // two sorters are alive at once while ranking
+    let sorter_memory = memory / 2;
+
+    // ranks by index, starting from the first items of each suffix packed together,
+    // 0 being past the end
+    let mut ranks = NamedTempFile::new_in(&directory)?;
+    let mut size = 0usize;
+    {
+        let mut writer = BufWriter::with_capacity(BUFFER_SIZE, ranks.as_file_mut());
+        let mut window: VecDeque<u8> = VecDeque::with_capacity(PACKED_ITEMS);
+        let mut index = 0u64;
+
+        let mut write_rank = |window: &VecDeque<u8>, writer: &mut BufWriter<_>| {
+            let rank = window
+                .iter()
+                .fold(0u64, |rank, &byte| (rank << 9) | (byte as u64 + 1));
+            let record = RankRecord {
+                index,
+                rank: rank << (9 * (PACKED_ITEMS - window.len())),
+                sorted: false,
+            };
+            index += 1;
+            record.write(writer)
+        };
+
+        for byte in BufReader::with_capacity(BUFFER_SIZE, reader).bytes() {
+            window.push_back(byte?);
+            size += 1;
+
+            if size >= I::MAX {
+                return Err(Error::SuffixIndexOverflow {
+                    size,
+                    index_size: I::SIZE,
+                });
+            }
+
+            if window.len() == PACKED_ITEMS {
+                write_rank(&window, &mut writer)?;
+                window.pop_front();
+            }
+        }
+
+        while !window.is_empty() {
+            write_rank(&window, &mut writer)?;
+            window.pop_front();
+        }
+        writer.flush()?;
+    }
+
+    let mut h = PACKED_ITEMS;
+    // the first round sorts all suffixes by their packed items, as a single group
+    let mut packed = true;
+
+    loop {
+        // pair the rank of each suffix left with the one `h` items later
+        let mut pairs = ExternalSorter::<PairRecord>::new(&directory, sorter_memory);
+        {
+            let mut current = RecordReader::<RankRecord>::open(ranks.path(), 0)?;
+            let mut next = RecordReader::<RankRecord>::open(ranks.path(), h)?;
+
+            while let Some(record) = current.next()? {
+                let next_rank = next.next()?.map_or(0, |next| next.rank);
+
+                if !record.sorted {
+                    pairs.push(PairRecord {
+                        rank: record.rank,
+                        next_rank,
+                        index: record.index,
+                    })?;
+                }
+            }
+        }
+
+        // rank suffixes by their pair, a rank being one past where its group starts in the
+        // suffix array, so that ranks of sorted suffixes stay valid in later rounds
+        let mut updates = ExternalSorter::<RankRecord>::new(&directory, sorter_memory);
+        let mut unsorted_count = 0usize;
+        {
+            let mut sorted = pairs.finish()?;
+            let mut last: Option<PairRecord> = None;
+            // suffixes before this one in its group of the previous round
+            let mut offset = 0u64;
+            let mut rank = 0u64;
+            // the first suffix of a group, sorted unless another one joins it
+            let mut first: Option<RankRecord> = None;
+
+            while let Some(record) = sorted.next()? {
+                if !last.is_some_and(|last| packed || last.rank == record.rank) {
+                    offset = 0;
+                }
+
+                if last.is_some_and(|last| last.pair() == record.pair()) {
+                    if let Some(first) = first.take() {
+                        updates.push(first)?;
+                        unsorted_count += 1;
+                    }
+                    updates.push(RankRecord {
+                        index: record.index,
+                        rank,
+                        sorted: false,
+                    })?;
+                    unsorted_count += 1;
+                } else {
+                    if let Some(first) = first.take() {
+                        updates.push(RankRecord {
+                            sorted: true,
+                            ..first
+                        })?;
+                    }
+
+                    rank = if packed { 1 } else { record.rank } + offset;
+                    first = Some(RankRecord {
+                        index: record.index,
+                        rank,
+                        sorted: false,
+                    });
+                }
+
+                offset += 1;
+                last = Some(record);
+            }
+
+            if let Some(first) = first {
+                updates.push(RankRecord {
+                    sorted: true,
+                    ..first
+                })?;
+            }
+        }
+
+        // apply the new ranks, both sides being ordered by index
+        let mut next_ranks = NamedTempFile::new_in(&directory)?;
+        {
+            let mut writer = BufWriter::with_capacity(BUFFER_SIZE, next_ranks.as_file_mut());
+            let mut current = RecordReader::<RankRecord>::open(ranks.path(), 0)?;
+            let mut updates = updates.finish()?;
+            let mut update = updates.next()?;
+
+            while let Some(record) = current.next()? {
+                match update {
+                    Some(next) if next.index == record.index => {
+                        next.write(&mut writer)?;
+                        update = updates.next()?;
+                    }
+                    _ => record.write(&mut writer)?,
+                }
+            }
+            writer.flush()?;
+        }
+        ranks = next_ranks;
+
+        if unsorted_count == 0 {
+            break;
+        }
+
+        packed = false;
+        h *= 2;
+    }
+
+    // every rank is now one past the place of its suffix
+    let mut suffixes = ExternalSorter::<SuffixRecord>::new(&directory, memory);
+    {
+        let mut records = RecordReader::<RankRecord>::open(ranks.path(), 0)?;
+        while let Some(record) = records.next()? {
+            suffixes.push(SuffixRecord {
+                rank: record.rank,
+                index: record.index,
+            })?;
+        }
+    }
+    drop(ranks);
+
+    {
+        let mut writer = BufWriter::with_capacity(BUFFER_SIZE, File::create(path)?);
+        let mut sorted = suffixes.finish()?;
+        while let Some(record) = sorted.next()? {
+            I::from_usize(record.index as usize).write(&mut writer)?;
+        }
+        writer.flush()?;
+    }
+
+    SuffixArrayFile::open(path)
+}
+
+// Fixed-size record spilled to disk, ordered by its leading fields.
+trait Record: Copy + Ord {
+    const SIZE: usize;
+
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()>;
+
+    fn read(bytes: &[u8]) -> Self;
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct RankRecord {
+    index: u64,
+    rank: u64,
+    // alone in its group, so its rank is final
+    sorted: bool,
+}
+
+impl Record for RankRecord {
+    const SIZE: usize = 17;
+
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.index.to_le_bytes())?;
+        writer.write_all(&self.rank.to_le_bytes())?;
+        writer.write_all(&[self.sorted as u8])
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            index: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            rank: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            sorted: bytes[16] != 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct PairRecord {
+    rank: u64,
+    next_rank: u64,
+    index: u64,
+}
+
+impl PairRecord {
+    fn pair(&self) -> (u64, u64) {
+        (self.rank, self.next_rank)
+    }
+}
+
+impl Record for PairRecord {
+    const SIZE: usize = 24;
+
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.rank.to_le_bytes())?;
+        writer.write_all(&self.next_rank.to_le_bytes())?;
+        writer.write_all(&self.index.to_le_bytes())
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            rank: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            next_rank: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            index: u64::from_le_bytes(bytes[16..24].try_into().unwrap()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct SuffixRecord {
+    rank: u64,
+    index: u64,
+}
+
+impl Record for SuffixRecord {
+    const SIZE: usize = 16;
+
+    fn write(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.rank.to_le_bytes())?;
+        writer.write_all(&self.index.to_le_bytes())
+    }
+
+    fn read(bytes: &[u8]) -> Self {
+        Self {
+            rank: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            index: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+        }
+    }
+}
+
+struct RecordReader<R: Record> {
+    reader: BufReader<File>,
+    bytes: Vec<u8>,
+    phantom: PhantomData<R>,
+}
+
+impl<R: Record> RecordReader<R> {
+    // Opens a reader of its own on `path`, starting from the `skip`th record.
+    fn open(path: &Path, skip: usize) -> Result<Self> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start((skip * R::SIZE) as u64))?;
+
+        Ok(Self {
+            reader: BufReader::with_capacity(BUFFER_SIZE, file),
+            bytes: vec![0u8; R::SIZE],
+            phantom: PhantomData,
+        })
+    }
+
+    fn next(&mut self) -> Result<Option<R>> {
+        match self.reader.read_exact(&mut self.bytes) {
+            Ok(()) => Ok(Some(R::read(&self.bytes))),
+            Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(None),
+            Err(error) => Err(error.into()),
+        }
+    }
+}
+
+// Sorts records in runs of up to `memory` bytes spilled to disk. Runs are closed once written,
+// and merged into one as soon as `fan_in` of them went through as many merges, so that only the
+// runs of a merge are open at once. The rest are merged back on `finish`.
+struct ExternalSorter<R: Record> {
+    directory: PathBuf,
+    memory: usize,
+    fan_in: usize,
+    buffer: Vec<R>,
+    // runs by the number of merges they went through
+    levels: Vec<Vec<TempPath>>,
+}
+
+impl<R: Record> ExternalSorter<R> {
+    fn new(directory: &Path, memory: usize) -> Self {
+        Self {
+            directory: directory.to_path_buf(),
+            memory,
+            // merge as many runs at once as there are buffers within the budget
+            fan_in: (memory / BUFFER_SIZE).clamp(2, MAX_FAN_IN),
+            buffer: vec![],
+            levels: vec![],
+        }
+    }
+
+    fn push(&mut self, record: R) -> Result<()> {
+        self.buffer.push(record);
+
+        // records take more room in memory than on disk, being padded to their alignment
+        if self.buffer.len() * size_of::<R>() >= self.memory {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    fn spill(&mut self) -> Result<()> {
+        self.buffer.sort_unstable();
+
+        let (file, run) = NamedTempFile::new_in(&self.directory)?.into_parts();
+        let mut writer = BufWriter::with_capacity(BUFFER_SIZE, file);
+        for record in self.buffer.iter() {
+            record.write(&mut writer)?;
+        }
+        writer.flush()?;
+
+        self.buffer.clear();
+        self.add_run(run, 0)
+    }
+
+    fn add_run(&mut self, mut run: TempPath, mut level: usize) -> Result<()> {
+        loop {
+            if self.levels.len() <= level {
+                self.levels.push(vec![]);
+            }
+            self.levels[level].push(run);
+
+            if self.levels[level].len() < self.fan_in {
+                return Ok(());
+            }
+
+            let runs = std::mem::take(&mut self.levels[level]);
+            run = self.merge_runs(&runs)?;
+            level += 1;
+        }
+    }
+
+    // Merges `runs` into a new run, removing them once it is written.
+    fn merge_runs(&self, runs: &[TempPath]) -> Result<TempPath> {
+        let mut merge = Merge::<R>::open(runs)?;
+
+        let (file, run) = NamedTempFile::new_in(&self.directory)?.into_parts();
+        let mut writer = BufWriter::with_capacity(BUFFER_SIZE, file);
+        while let Some(record) = merge.next()? {
+            record.write(&mut writer)?;
+        }
+        writer.flush()?;
+
+        Ok(run)
+    }
+
+    fn finish(mut self) -> Result<SortedRecords<R>> {
+        if self.levels.is_empty() {
+            self.buffer.sort_unstable();
+            return Ok(SortedRecords::Memory(self.buffer.into_iter()));
+        }
+
+        if !self.buffer.is_empty() {
+            self.spill()?;
+        }
+        self.buffer = vec![];
+
+        // every level holds fewer than `fan_in` runs, the smallest ones first
+        let mut runs: Vec<TempPath> = std::mem::take(&mut self.levels)
+            .into_iter()
+            .flatten()
+            .collect();
+
+        while runs.len() > self.fan_in {
+            let merged = self.merge_runs(&runs[..self.fan_in])?;
+            runs.drain(..self.fan_in);
+            runs.push(merged);
+        }
+
+        Ok(SortedRecords::Disk {
+            merge: Merge::open(&runs)?,
+            _runs: runs,
+        })
+    }
+}
+
+enum SortedRecords<R: Record> {
+    Memory(std::vec::IntoIter<R>),
+    Disk {
+        merge: Merge<R>,
+        // removed once the merge is dropped
+        _runs: Vec<TempPath>,
+    },
+}
+
+impl<R: Record> SortedRecords<R> {
+    fn next(&mut self) -> Result<Option<R>> {
+        match self {
+            Self::Memory(records) => Ok(records.next()),
+            Self::Disk { merge, .. } => merge.next(),
+        }
+    }
+}
+
+struct Merge<R: Record> {
+    readers: Vec<RecordReader<R>>,
+    heap: BinaryHeap<Reverse<(R, usize)>>,
+}
+
+impl<R: Record> Merge<R> {
+    fn open(runs: &[TempPath]) -> Result<Self> {
+        let mut readers = vec![];
+        let mut heap = BinaryHeap::new();
+
+        for (run_index, run) in runs.iter().enumerate() {
+            let mut reader = RecordReader::open(run, 0)?;
+            if let Some(record) = reader.next()? {
+                heap.push(Reverse((record, run_index)));
+            }
+            readers.push(reader);
+        }
+
+        Ok(Self { readers, heap })
+    }
+
+    fn next(&mut self) -> Result<Option<R>> {
+        let Some(Reverse((record, run_index))) = self.heap.pop() else {
+            return Ok(None);
+        };
+
+        if let Some(next) = self.readers[run_index].next()? {
+            self.heap.push(Reverse((next, run_index)));
+        }
+        Ok(Some(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::{
+        suffix_array,
+        tests::{fibonacci, runs, Random},
+        U8Bucket,
+    };
+
+    // Small enough a budget for every sort to spill and cascade merges.
+    const MEMORY: usize = 1 << 12;
+
+    fn check(data: &[u8], memory: usize) {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("suffix_array");
+
+        let external = external_suffix_array::<u32>(data, &path, memory).unwrap();
+        let expected: Vec<u32> = suffix_array(data, &U8Bucket);
+        assert_eq!(&external[..], &expected[..], "{:?}", data);
+
+        // only the suffix array is left behind
+        drop(external);
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..200 {
+            check(&random.bytes(300), MEMORY);
+        }
+        for _ in 0..5 {
+            check(&random.bytes(20000), MEMORY);
+        }
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        for data in runs() {
+            check(&data, MEMORY);
+        }
+        check(&vec![0u8; 20000], MEMORY);
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(20000) {
+            check(&data, MEMORY);
+        }
+    }
+
+    #[test]
+    fn in_memory_sorts() {
+        let mut random = Random(2);
+        check(&random.bytes(20000), 1 << 24);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{borrow::Cow, str::FromStr};
+
+use super::splitter::Splitting;
+
+// Reversible transform applied to every block before compression and undone after extraction.
+// `id` and `parameter` are recorded in the stream header to rebuild the filter.
+pub trait Filter {
+    fn id(&self) -> u8;
+
+    fn parameter(&self) -> usize;
+
+    fn encoded_size(&self, size: usize) -> usize {
+        size
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8>;
+
+    fn decode(&self, data: &[u8]) -> Vec<u8>;
+}
+
+// Replaces every byte by its difference from the byte `stride` positions before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Delta {
+    pub stride: usize,
+}
+
+impl Filter for Delta {
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn parameter(&self) -> usize {
+        self.stride
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        (0..data.len())
+            .map(|index| match index.checked_sub(self.stride) {
+                Some(before) => data[index].wrapping_sub(data[before]),
+                None => data[index],
+            })
+            .collect()
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        let mut decoded = data.to_vec();
+
+        for index in self.stride..decoded.len() {
+            decoded[index] = decoded[index].wrapping_add(decoded[index - self.stride]);
+        }
+
+        decoded
+    }
+}
+
+// Groups the n-th bytes of all records of `width` bytes together.
+// Trailing bytes short of a whole record are kept as they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shuffle {
+    pub width: usize,
+}
+
+impl Filter for Shuffle {
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn parameter(&self) -> usize {
+        self.width
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let records = data.len() / self.width;
+        let mut encoded = Vec::with_capacity(data.len());
+
+        for offset in 0..self.width {
+            encoded.extend((0..records).map(|record| data[record * self.width + offset]));
+        }
+        encoded.extend_from_slice(&data[(records * self.width)..]);
+
+        encoded
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        let records = data.len() / self.width;
+        let mut decoded = data.to_vec();
+
+        for offset in 0..self.width {
+            for record in 0..records {
+                decoded[record * self.width + offset] = data[offset * records + record];
+            }
+        }
+
+        decoded
+    }
+}
+
+// Turns the relative targets of x86 `call` and `jmp` (E8 and E9) into absolute ones,
+// so repeated calls to the same function become repeated bytes. Only targets within
+// 16 MiB are converted, which keeps the conversion reversible.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Bcj;
+
+impl Bcj {
+    fn convert(data: &[u8], encode: bool) -> Vec<u8> {
+        let mut converted = data.to_vec();
+        let mut index = 0;
+
+        while index + 5 <= converted.len() {
+            if !matches!(converted[index], 0xE8 | 0xE9)
+                || !matches!(converted[index + 4], 0x00 | 0xFF)
+            {
+                index += 1;
+                continue;
+            }
+
+            let operand = &mut converted[(index + 1)..(index + 5)];
+            let value = i32::from_le_bytes(operand.try_into().unwrap());
+            let next = (index + 5) as i32;
+
+            let value = if encode {
+                value.wrapping_add(next)
+            } else {
+                value.wrapping_sub(next)
+            };
+
+            // keep the top byte 0x00 or 0xFF by sign extending from 25 bits
+            operand.copy_from_slice(&((value << 7) >> 7).to_le_bytes());
+            index += 5;
+        }
+
+        converted
+    }
+}
+
+impl Filter for Bcj {
+    fn id(&self) -> u8 {
+        2
+    }
+
+    fn parameter(&self) -> usize {
+        0
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        Self::convert(data, true)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        Self::convert(data, false)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterStage {
+    Delta(Delta),
+    Shuffle(Shuffle),
+    Bcj(Bcj),
+    Split(Splitting),
+}
+
+impl FilterStage {
+    pub fn from_id(id: u8, parameter: usize) -> Option<Self> {
+        match (id, parameter) {
+            (0, stride) if stride > 0 => Some(Self::Delta(Delta { stride })),
+            (1, width) if width > 0 => Some(Self::Shuffle(Shuffle { width })),
+            (2, 0) => Some(Self::Bcj(Bcj)),
+            (3, bits) => Splitting::from_bits(bits).map(Self::Split),
+            _ => None,
+        }
+    }
+
+    fn filter(&self) -> &dyn Filter {
+        match self {
+            Self::Delta(filter) => filter,
+            Self::Shuffle(filter) => filter,
+            Self::Bcj(filter) => filter,
+            Self::Split(filter) => filter,
+        }
+    }
+}
+
+impl Filter for FilterStage {
+    fn id(&self) -> u8 {
+        self.filter().id()
+    }
+
+    fn parameter(&self) -> usize {
+        self.filter().parameter()
+    }
+
+    fn encoded_size(&self, size: usize) -> usize {
+        self.filter().encoded_size(size)
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        self.filter().encode(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        self.filter().decode(data)
+    }
+}
+
+// Written as `delta:STRIDE`, `shuffle:WIDTH`, `bcj` or `split:BITS`.
+impl FromStr for FilterStage {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (name, parameter) = value.split_once(':').unwrap_or((value, ""));
+        let parameter = |default: usize| {
+            if parameter.is_empty() {
+                return Ok(default);
+            }
+
+            parameter
+                .parse::<usize>()
+                .map_err(|_| format!("invalid parameter in filter `{}`", value))
+        };
+
+        let stage = match name {
+            "delta" => Self::from_id(0, parameter(1)?),
+            "shuffle" => Self::from_id(1, parameter(4)?),
+            "bcj" => Self::from_id(2, parameter(0)?),
+            "split" => Self::from_id(3, parameter(4)?),
+            _ => return Err(format!("unknown filter `{}`", value)),
+        };
+
+        stage.ok_or(format!("invalid parameter in filter `{}`", value))
+    }
+}
+
+pub fn encoded_size(filters: &[FilterStage], size: usize) -> usize {
+    filters
+        .iter()
+        .fold(size, |size, filter| filter.encoded_size(size))
+}
+
+pub fn encode_all<'a>(filters: &[FilterStage], data: &'a [u8]) -> Cow<'a, [u8]> {
+    filters.iter().fold(Cow::Borrowed(data), |data, filter| {
+        Cow::Owned(filter.encode(&data))
+    })
+}
+
+pub fn decode_all<'a>(filters: &[FilterStage], data: &'a [u8]) -> Cow<'a, [u8]> {
+    filters
+        .iter()
+        .rev()
+        .fold(Cow::Borrowed(data), |data, filter| {
+            Cow::Owned(filter.decode(&data))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    fn check_roundtrip(filter: &impl Filter, data: &[u8]) {
+        let encoded = filter.encode(data);
+        assert_eq!(encoded.len(), filter.encoded_size(data.len()));
+        assert_eq!(filter.decode(&encoded), data);
+    }
+
+    // Every length up to a few records of the parameters tested, and one long input.
+    fn inputs(random: &mut Random) -> Vec<Vec<u8>> {
+        let mut inputs: Vec<Vec<u8>> = (0..40)
+            .map(|size| (0..size).map(|_| random.next() as u8).collect())
+            .collect();
+        inputs.push(random.bytes(1000));
+        inputs
+    }
+
+    #[test]
+    fn delta_roundtrips() {
+        let mut random = Random(1);
+        for data in inputs(&mut random) {
+            for stride in 1..10 {
+                check_roundtrip(&Delta { stride }, &data);
+            }
+            check_roundtrip(&Delta { stride: 1 << 20 }, &data);
+        }
+
+        let encoded = Delta { stride: 2 }.encode(&[1, 2, 4, 3, 4, 3]);
+        assert_eq!(encoded, [1, 2, 3, 1, 0, 0]);
+    }
+
+    #[test]
+    fn shuffle_roundtrips() {
+        let mut random = Random(2);
+        for data in inputs(&mut random) {
+            for width in 1..10 {
+                check_roundtrip(&Shuffle { width }, &data);
+            }
+            check_roundtrip(&Shuffle { width: 1 << 20 }, &data);
+        }
+
+        // the trailing bytes short of a record stay last
+        let encoded = Shuffle { width: 3 }.encode(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(encoded, [1, 4, 2, 5, 3, 6, 7, 8]);
+    }
+
+    #[test]
+    fn bcj_roundtrips() {
+        let mut random = Random(3);
+        for data in inputs(&mut random) {
+            check_roundtrip(&Bcj, &data);
+        }
+
+        // dense calls and jumps whose targets cross the 25-bit range either way
+        for _ in 0..1000 {
+            let mut data = vec![];
+            while data.len() < 200 {
+                match random.next() % 4 {
+                    0 => data.push(random.next() as u8),
+                    opcode => {
+                        let operand = (random.next() as i32) >> 7;
+                        let operand = match opcode {
+                            1 => operand,
+                            2 => (1 << 24) - 1 - (operand & 0xFF),
+                            _ => -(1 << 24) + (operand & 0xFF),
+                        };
+                        data.push([0xE8, 0xE9][(random.next() % 2) as usize]);
+                        data.extend_from_slice(&operand.to_le_bytes());
+                    }
+                }
+            }
+            check_roundtrip(&Bcj, &data);
+        }
+    }
+
+    #[test]
+    fn bcj_sign_extends_targets() {
+        // relative -10 from the call at 5 becomes absolute 0
+        let encoded = Bcj.encode(&[0x90, 0x90, 0x90, 0x90, 0x90, 0xE8, 0xF6, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(encoded[6..], [0x00, 0x00, 0x00, 0x00]);
+
+        // relative 2^24 - 1 from the jump at 0 wraps around to -2^24 + 4
+        let data = [0xE9, 0xFF, 0xFF, 0xFF, 0x00, 0xE8, 0x00, 0x00, 0x00, 0x01];
+        let encoded = Bcj.encode(&data);
+        assert_eq!(encoded[1..5], (-(1 << 24) + 4i32).to_le_bytes());
+        // a top byte other than 0x00 or 0xFF is not an operand the filter converts
+        assert_eq!(encoded[5..], data[5..]);
+        assert_eq!(Bcj.decode(&encoded), data);
+    }
+
+    #[test]
+    fn filter_chains_roundtrip() {
+        let stages: Vec<FilterStage> = ["delta:3", "shuffle:5", "bcj", "split:2"]
+            .iter()
+            .map(|stage| stage.parse().unwrap())
+            .collect();
+        for stage in stages.iter() {
+            assert_eq!(
+                FilterStage::from_id(stage.id(), stage.parameter()),
+                Some(*stage)
+            );
+        }
+
+        let mut random = Random(4);
+        for data in inputs(&mut random) {
+            let encoded = encode_all(&stages, &data);
+            assert_eq!(encoded.len(), encoded_size(&stages, data.len()));
+            assert_eq!(decode_all(&stages, &encoded), data);
+        }
+    }
+}
@@ -0,0 +1,680 @@
+use std::{
+    io::{Read, Write},
+    str::FromStr,
+};
+
+use crate::error::{Error, Result};
+
+use super::{
+    bwt::BWT,
+    checksum::Checksum,
+    filter::{Filter, FilterStage},
+    head::{Common, Head},
+    lz77::{CompressOptions, LZ77},
+    lz77_huffman::LZ77Huffman,
+    lz77_range::LZ77Range,
+    lzss::LZSSLayout,
+    stream::{Decoder, Encoder},
+};
+
+// Legacy headerless streams always start with a `PackedBits` record (leading 1 bit),
+// so a magic number starting with a 0 bit can never be mistaken for one.
+pub const MAGIC: [u8; 4] = *b"AZKI";
+pub const VERSION: u8 = 1;
+
+pub const DEFAULT_BLOCK_SIZE: usize = 1 << 20;
+
+const FLAG_ORIGINAL_SIZE: u8 = 0x01;
+const FLAG_WINDOW: u8 = 0x02;
+const FLAG_FILTERS: u8 = 0x04;
+const FLAG_LINKED: u8 = 0x08;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Codec {
+    LZ77,
+    LZ77Huffman,
+    LZ77Range,
+    BWT,
+    LZSS(LZSSLayout),
+    Stored,
+    // Chosen per block among `AUTO_CANDIDATES`, recorded in every block header.
+    Auto,
+}
+
+// The byte oriented formats, which are fast enough to try them all on every block.
+// Storing the block as is comes last, so it is only kept when nothing else is smaller.
+const AUTO_CANDIDATES: [Codec; 6] = [
+    Codec::LZ77,
+    Codec::LZSS(LZSSLayout::C2L13),
+    Codec::LZSS(LZSSLayout::C3L12),
+    Codec::LZSS(LZSSLayout::C4L11),
+    Codec::LZSS(LZSSLayout::L7C8),
+    Codec::Stored,
+];
+
+impl Codec {
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::LZ77 => 0,
+            Self::LZ77Huffman => 1,
+            Self::LZ77Range => 2,
+            Self::BWT => 3,
+            Self::LZSS(LZSSLayout::C2L13) => 4,
+            Self::LZSS(LZSSLayout::C3L12) => 5,
+            Self::LZSS(LZSSLayout::C4L11) => 6,
+            Self::LZSS(LZSSLayout::L7C8) => 7,
+            Self::Stored => 8,
+            Self::Auto => 9,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::LZ77),
+            1 => Some(Self::LZ77Huffman),
+            2 => Some(Self::LZ77Range),
+            3 => Some(Self::BWT),
+            4 => Some(Self::LZSS(LZSSLayout::C2L13)),
+            5 => Some(Self::LZSS(LZSSLayout::C3L12)),
+            6 => Some(Self::LZSS(LZSSLayout::C4L11)),
+            7 => Some(Self::LZSS(LZSSLayout::L7C8)),
+            8 => Some(Self::Stored),
+            9 => Some(Self::Auto),
+            _ => None,
+        }
+    }
+
+    // Compresses `data[start..]`, where matches may reach back into `data[..start]`.
+    // Returns the codec the data ended up compressed with, which only differs for `Auto`.
+    pub fn compress(
+        &self,
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<Self> {
+        match self {
+            Self::LZ77 => LZ77::compress(data, start, options, writer)?,
+            Self::LZ77Huffman => LZ77Huffman::compress(data, start, options, writer)?,
+            Self::LZ77Range => LZ77Range::compress(data, start, options, writer)?,
+            Self::BWT => BWT::compress(&data[start..], writer)?,
+            Self::LZSS(layout) => layout.compress(data, start, options, writer)?,
+            Self::Stored => writer.write_all(&data[start..])?,
+            Self::Auto => return Self::compress_smallest(data, start, options, writer),
+        }
+
+        Ok(*self)
+    }
+
+    fn compress_smallest(
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<Self> {
+        let mut smallest: Option<(Self, Vec<u8>)> = None;
+
+        for codec in AUTO_CANDIDATES {
+            let mut compressed = vec![];
+            codec.compress(data, start, options, &mut compressed)?;
+
+            if smallest
+                .as_ref()
+                .is_none_or(|(_, smallest)| compressed.len() < smallest.len())
+            {
+                smallest = Some((codec, compressed));
+            }
+        }
+
+        let (codec, compressed) = smallest.unwrap();
+        writer.write_all(&compressed)?;
+
+        Ok(codec)
+    }
+
+    // Extracts a block compressed after `history`, the data `compress` was given before `start`.
+    pub fn extract(
+        &self,
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        match self {
+            Self::LZ77 => LZ77::extract(buffer, history, max_size, window, writer),
+            Self::LZ77Huffman => LZ77Huffman::extract(buffer, history, max_size, window, writer),
+            Self::LZ77Range => LZ77Range::extract(buffer, history, max_size, window, writer),
+            Self::BWT => BWT::extract(buffer, max_size, writer),
+            Self::LZSS(layout) => layout.extract(buffer, history, max_size, window, writer),
+            Self::Stored => {
+                if buffer.len() > max_size {
+                    return Err(Error::SizeMismatch {
+                        expected: max_size,
+                        actual: buffer.len(),
+                    });
+                }
+
+                writer.write_all(buffer)?;
+                Ok(buffer.len())
+            }
+            // blocks always record the codec they were compressed with
+            Self::Auto => Err(Error::UnknownCodec(self.id())),
+        }
+    }
+
+    // Describes the tokens of a block extracting to at most `max_size` bytes after `history`.
+    pub fn dump(
+        &self,
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        match self {
+            Self::LZ77 => LZ77::dump(buffer, writer),
+            Self::LZ77Huffman => LZ77Huffman::dump(buffer, writer),
+            Self::LZ77Range => LZ77Range::dump(buffer, history, max_size, writer),
+            Self::BWT => BWT::dump(buffer, max_size, writer),
+            Self::LZSS(layout) => layout.dump(buffer, writer),
+            Self::Stored => {
+                writeln!(writer, "{:>8x}: stored size: {:x}", 0, buffer.len())?;
+                Ok(())
+            }
+            Self::Auto => Err(Error::UnknownCodec(self.id())),
+        }
+    }
+}
+
+impl FromStr for Codec {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "lz77" => Ok(Self::LZ77),
+            "lz77-huffman" => Ok(Self::LZ77Huffman),
+            "lz77-range" => Ok(Self::LZ77Range),
+            "bwt" => Ok(Self::BWT),
+            "lzss" => Ok(Self::LZSS(LZSSLayout::C3L12)),
+            "stored" => Ok(Self::Stored),
+            "auto" => Ok(Self::Auto),
+            _ => match value.strip_prefix("lzss:") {
+                Some(layout) => Ok(Self::LZSS(layout.parse()?)),
+                None => Err(format!("unknown codec `{}`", value)),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Header {
+    pub version: u8,
+    pub codec: Codec,
+    pub checksum: Checksum,
+    pub filters: Vec<FilterStage>,
+    pub block_size: usize,
+    pub window: Option<usize>,
+    // Whether matches reach back into the blocks before, see `history_size`.
+    pub linked: bool,
+    pub original_size: Option<usize>,
+}
+
+impl Header {
+    // Bytes before a block, as the filters left them, its matches may reach back into.
+    // Linked streams carry the window from block to block, up to a block size back,
+    // so extraction never holds more than two blocks.
+    pub fn history_size(&self) -> usize {
+        match self.window {
+            Some(window) if self.linked => window.min(self.block_size),
+            _ => 0,
+        }
+    }
+}
+
+pub struct HeaderLayout;
+
+impl HeaderLayout {
+    pub fn check(buffer: &[u8]) -> bool {
+        buffer.starts_with(&MAGIC)
+    }
+
+    pub fn prepare() -> Header {
+        Header {
+            version: Default::default(),
+            codec: Codec::LZ77,
+            checksum: Checksum::None,
+            filters: vec![],
+            block_size: Default::default(),
+            window: Default::default(),
+            linked: Default::default(),
+            original_size: Default::default(),
+        }
+    }
+
+    pub fn write(data: &Header, writer: &mut impl Write) -> std::io::Result<usize> {
+        let mut flags = 0;
+        if data.original_size.is_some() {
+            flags |= FLAG_ORIGINAL_SIZE;
+        }
+        if data.window.is_some() {
+            flags |= FLAG_WINDOW;
+        }
+        if !data.filters.is_empty() {
+            flags |= FLAG_FILTERS;
+        }
+        if data.linked {
+            flags |= FLAG_LINKED;
+        }
+
+        writer.write_all(&MAGIC)?;
+        writer.write_all(&[data.version, data.codec.id(), data.checksum.id(), flags])?;
+        let mut cursor = MAGIC.len() + 4;
+
+        // filters in the order they are applied on compression, each one with its parameter
+        if !data.filters.is_empty() {
+            writer.write_all(&[data.filters.len() as u8])?;
+            cursor += 1;
+
+            for filter in data.filters.iter() {
+                writer.write_all(&[filter.id()])?;
+                cursor += 1 + Head::<Common>::write(&filter.parameter(), writer)?;
+            }
+        }
+
+        cursor += Head::<Common>::write(&data.block_size, writer)?;
+
+        if let Some(window) = &data.window {
+            cursor += Head::<Common>::write(window, writer)?;
+        }
+
+        if let Some(original_size) = &data.original_size {
+            cursor += Head::<Common>::write(original_size, writer)?;
+        }
+
+        Ok(cursor)
+    }
+
+    pub fn read(reader: &mut impl Read, data: &mut Header) -> Result<usize> {
+        let mut buffer = [0u8; MAGIC.len() + 4];
+        reader.read_exact(&mut buffer)?;
+
+        if !Self::check(&buffer) {
+            return Err(Error::NotFrozen);
+        }
+
+        let [version, codec, checksum, flags] = [buffer[4], buffer[5], buffer[6], buffer[7]];
+        let mut cursor = buffer.len();
+
+        if version != VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+
+        let Some(codec) = Codec::from_id(codec) else {
+            return Err(Error::UnknownCodec(codec));
+        };
+
+        let Some(checksum) = Checksum::from_id(checksum) else {
+            return Err(Error::UnknownChecksum(checksum));
+        };
+
+        let unknown_flags =
+            flags & !(FLAG_ORIGINAL_SIZE | FLAG_WINDOW | FLAG_FILTERS | FLAG_LINKED);
+        if unknown_flags != 0 {
+            return Err(Error::UnknownFlags(unknown_flags));
+        }
+
+        data.version = version;
+        data.codec = codec;
+        data.checksum = checksum;
+        data.linked = flags & FLAG_LINKED != 0;
+
+        data.filters.clear();
+        if flags & FLAG_FILTERS != 0 {
+            let mut filter_count = [0u8];
+            reader.read_exact(&mut filter_count)?;
+            cursor += 1;
+
+            for _ in 0..filter_count[0] {
+                let mut id = [0u8];
+                reader.read_exact(&mut id)?;
+                let mut parameter = 0;
+                cursor += 1 + Head::<Common>::read(reader, &mut parameter)?;
+
+                let Some(filter) = FilterStage::from_id(id[0], parameter) else {
+                    return Err(Error::InvalidFilter {
+                        id: id[0],
+                        parameter,
+                    });
+                };
+                data.filters.push(filter);
+            }
+        }
+
+        cursor += Head::<Common>::read(reader, &mut data.block_size)?;
+
+        data.window = if flags & FLAG_WINDOW != 0 {
+            let mut window = 0;
+            cursor += Head::<Common>::read(reader, &mut window)?;
+            Some(window)
+        } else {
+            None
+        };
+
+        data.original_size = if flags & FLAG_ORIGINAL_SIZE != 0 {
+            let mut original_size = 0;
+            cursor += Head::<Common>::read(reader, &mut original_size)?;
+            Some(original_size)
+        } else {
+            None
+        };
+
+        Ok(cursor)
+    }
+}
+
+// Every block is prefixed with its original and compressed sizes, and followed by
+// the checksum of its original data. A block with an original size of 0 terminates
+// the stream and is followed by the checksum of the whole original data instead.
+// Streams frozen with `Codec::Auto` also record the codec id of every block.
+#[derive(Debug)]
+pub struct BlockHeader {
+    pub original_size: usize,
+    pub compressed_size: usize,
+    pub codec: Option<Codec>,
+}
+
+pub struct BlockHeaderLayout;
+
+impl BlockHeaderLayout {
+    pub fn prepare() -> BlockHeader {
+        BlockHeader {
+            original_size: Default::default(),
+            compressed_size: Default::default(),
+            codec: Default::default(),
+        }
+    }
+
+    pub fn write(data: &BlockHeader, writer: &mut impl Write) -> std::io::Result<usize> {
+        let mut cursor = Head::<Common>::write(&data.original_size, writer)?;
+
+        if data.original_size > 0 {
+            cursor += Head::<Common>::write(&data.compressed_size, writer)?;
+
+            if let Some(codec) = &data.codec {
+                writer.write_all(&[codec.id()])?;
+                cursor += 1;
+            }
+        }
+
+        Ok(cursor)
+    }
+
+    // `codec` is the one of the stream, telling whether blocks record their own.
+    pub fn read(reader: &mut impl Read, data: &mut BlockHeader, codec: Codec) -> Result<usize> {
+        let mut cursor = Head::<Common>::read(reader, &mut data.original_size)?;
+
+        data.compressed_size = 0;
+        data.codec = None;
+
+        if data.original_size > 0 {
+            cursor += Head::<Common>::read(reader, &mut data.compressed_size)?;
+
+            if codec == Codec::Auto {
+                let mut id = [0u8];
+                reader.read_exact(&mut id)?;
+                cursor += 1;
+
+                data.codec = match Codec::from_id(id[0]) {
+                    Some(Codec::Auto) | None => return Err(Error::UnknownCodec(id[0])),
+                    codec => codec,
+                };
+            }
+        }
+
+        Ok(cursor)
+    }
+}
+
+#[derive(Clone)]
+pub struct FreezeOptions {
+    pub codec: Codec,
+    pub filters: Vec<FilterStage>,
+    pub block_size: usize,
+    pub compress: CompressOptions,
+    pub checksum: Checksum,
+    pub original_size: Option<usize>,
+    // Blocks compressed at once, each on a thread of its own.
+    pub threads: usize,
+}
+
+impl Default for FreezeOptions {
+    fn default() -> Self {
+        Self {
+            codec: Codec::LZ77,
+            filters: vec![],
+            block_size: DEFAULT_BLOCK_SIZE,
+            compress: Default::default(),
+            checksum: Checksum::Crc32,
+            original_size: None,
+            threads: 1,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct MicrowaveOptions {
+    pub verify: bool,
+    // Blocks extracted at once, each on a thread of its own, unless they are linked.
+    pub threads: usize,
+    // Farthest distance a match may reach back, along with the one in the header.
+    // With a window and a single thread, LZ77 blocks are extracted as they are read,
+    // keeping only the window in memory.
+    pub window: Option<usize>,
+}
+
+impl Default for MicrowaveOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            threads: 1,
+            window: None,
+        }
+    }
+}
+
+pub struct Frame;
+
+impl Frame {
+    pub fn freeze(
+        reader: &mut impl Read,
+        writer: &mut impl Write,
+        options: &FreezeOptions,
+    ) -> Result<()> {
+        let mut encoder = Encoder::new(writer, options.clone());
+        std::io::copy(reader, &mut encoder)?;
+        encoder.finish()?;
+
+        Ok(())
+    }
+
+    pub fn microwave(
+        reader: &mut impl Read,
+        writer: &mut impl Write,
+        options: &MicrowaveOptions,
+    ) -> Result<()> {
+        let mut decoder = Decoder::new(reader, options.clone());
+        std::io::copy(&mut decoder, writer)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    const BLOCK_SIZE: usize = 1000;
+    const BLOCK_COUNT: usize = 4;
+
+    fn sample() -> Vec<u8> {
+        let mut random = Random(1);
+        (0..BLOCK_COUNT * BLOCK_SIZE - 10)
+            .map(|_| (random.next() % 16) as u8)
+            .collect()
+    }
+
+    fn freeze(data: &[u8], codec: Codec, window: Option<usize>) -> Vec<u8> {
+        let options = FreezeOptions {
+            codec,
+            block_size: BLOCK_SIZE,
+            compress: CompressOptions {
+                window,
+                ..CompressOptions::with_level(4)
+            },
+            ..Default::default()
+        };
+        let mut frozen = vec![];
+        Frame::freeze(&mut Cursor::new(data), &mut frozen, &options).unwrap();
+        frozen
+    }
+
+    fn microwave(frozen: &[u8], verify: bool, threads: usize) -> Result<Vec<u8>> {
+        let options = MicrowaveOptions {
+            verify,
+            threads,
+            ..Default::default()
+        };
+        let mut extracted = vec![];
+        Frame::microwave(&mut Cursor::new(frozen), &mut extracted, &options)?;
+        Ok(extracted)
+    }
+
+    // Positions of every block payload and of the checksum following it, the trailer's last.
+    fn layout(frozen: &[u8]) -> (Vec<usize>, Vec<usize>) {
+        let mut reader = Cursor::new(frozen);
+        let mut header = HeaderLayout::prepare();
+        HeaderLayout::read(&mut reader, &mut header).unwrap();
+
+        let mut payloads = vec![];
+        let mut checksums = vec![];
+        loop {
+            let mut block_header = BlockHeaderLayout::prepare();
+            BlockHeaderLayout::read(&mut reader, &mut block_header, header.codec).unwrap();
+
+            let payload = reader.position() as usize;
+            let checksum = payload + block_header.compressed_size;
+            payloads.push(payload);
+            checksums.push(checksum);
+            reader.set_position((checksum + header.checksum.size()) as u64);
+
+            if block_header.original_size == 0 {
+                return (payloads, checksums);
+            }
+        }
+    }
+
+    fn flipped(frozen: &[u8], position: usize) -> Vec<u8> {
+        let mut frozen = frozen.to_vec();
+        frozen[position] ^= 0x10;
+        frozen
+    }
+
+    // Stored blocks and LZ77 blocks, the latter both extracted as they are read and in parallel.
+    const CASES: [(Codec, Option<usize>, usize); 4] = [
+        (Codec::Stored, None, 1),
+        (Codec::Stored, None, 3),
+        (Codec::LZ77, Some(BLOCK_SIZE), 1),
+        (Codec::LZ77, Some(BLOCK_SIZE), 3),
+    ];
+
+    #[test]
+    fn flipped_block_checksums_are_reported_at_their_block() {
+        let data = sample();
+
+        for (codec, window, threads) in CASES {
+            let frozen = freeze(&data, codec, window);
+            let (_, checksums) = layout(&frozen);
+            assert_eq!(checksums.len(), BLOCK_COUNT + 1);
+
+            for (block, &position) in checksums[..BLOCK_COUNT].iter().enumerate() {
+                let result = microwave(&flipped(&frozen, position), true, threads);
+                assert!(
+                    matches!(
+                        result,
+                        Err(Error::ChecksumMismatch { offset: Some(offset) })
+                            if offset == block * BLOCK_SIZE
+                    ),
+                    "{:?} {} block {}: {:?}",
+                    codec,
+                    threads,
+                    block,
+                    result.map(|extracted| extracted.len())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_block_bytes_are_reported_at_their_block() {
+        let data = sample();
+        let frozen = freeze(&data, Codec::Stored, None);
+        let (payloads, _) = layout(&frozen);
+
+        for threads in [1, 3] {
+            for (block, &position) in payloads[..BLOCK_COUNT].iter().enumerate() {
+                let result = microwave(&flipped(&frozen, position + 7), true, threads);
+                assert!(
+                    matches!(
+                        result,
+                        Err(Error::ChecksumMismatch { offset: Some(offset) })
+                            if offset == block * BLOCK_SIZE
+                    ),
+                    "{} block {}",
+                    threads,
+                    block
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn flipped_stream_checksums_are_reported_without_offset() {
+        let data = sample();
+
+        for (codec, window, threads) in CASES {
+            let frozen = freeze(&data, codec, window);
+            let result = microwave(&flipped(&frozen, frozen.len() - 1), true, threads);
+            assert!(
+                matches!(result, Err(Error::ChecksumMismatch { offset: None })),
+                "{:?} {}",
+                codec,
+                threads
+            );
+        }
+    }
+
+    #[test]
+    fn unverified_streams_skip_checksums() {
+        let data = sample();
+
+        for (codec, window, threads) in CASES {
+            let frozen = freeze(&data, codec, window);
+            let (_, checksums) = layout(&frozen);
+
+            for &position in checksums.iter() {
+                let extracted = microwave(&flipped(&frozen, position), false, threads).unwrap();
+                assert_eq!(extracted, data, "{:?} {}", codec, threads);
+            }
+        }
+
+        // corrupted blocks are extracted as they are
+        let frozen = freeze(&data, Codec::Stored, None);
+        let (payloads, _) = layout(&frozen);
+        let extracted = microwave(&flipped(&frozen, payloads[1]), false, 1).unwrap();
+        assert_eq!(extracted.len(), data.len());
+        assert_eq!(extracted[BLOCK_SIZE], data[BLOCK_SIZE] ^ 0x10);
+    }
+}
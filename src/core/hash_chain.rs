@@ -0,0 +1,89 @@
+use super::suffix_reference::LcpBack;
+
+const HASH_BYTES: usize = 3;
+const HASH_BITS: u32 = 16;
+const NONE: usize = usize::MAX;
+
+// Finds earlier occurrences through chains of positions sharing the hash of their first bytes.
+// Much cheaper to build than a `SuffixReference`, but only `depth` candidates are examined,
+// and only those at most `window` positions back. Matches are cut at `max_count` bytes.
+pub struct HashChain<'a> {
+    data: &'a [u8],
+    heads: Vec<usize>,
+    prevs: Vec<usize>,
+    inserted: usize,
+    depth: usize,
+    window: usize,
+    max_count: usize,
+}
+
+impl<'a> HashChain<'a> {
+    pub fn new(data: &'a [u8], depth: usize, window: usize, max_count: usize) -> Self {
+        Self {
+            data,
+            heads: vec![NONE; 1 << HASH_BITS],
+            prevs: vec![NONE; data.len()],
+            inserted: 0,
+            depth,
+            window,
+            max_count,
+        }
+    }
+
+    fn hash(&self, index: usize) -> Option<usize> {
+        let bytes = self.data.get(index..(index + HASH_BYTES))?;
+        let value = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]);
+        Some((value.wrapping_mul(0x9E3779B1) >> (u32::BITS - HASH_BITS)) as usize)
+    }
+
+    fn insert_until(&mut self, index: usize) {
+        while self.inserted < index {
+            if let Some(hash) = self.hash(self.inserted) {
+                self.prevs[self.inserted] = self.heads[hash];
+                self.heads[hash] = self.inserted;
+            }
+
+            self.inserted += 1;
+        }
+    }
+
+    // Longest earlier match of `data[index..]`, preferring the nearest one among equals.
+    // Queries must come in non-decreasing order of `index`.
+    pub fn back(&mut self, index: usize) -> Option<LcpBack> {
+        self.insert_until(index);
+
+        let hash = self.hash(index)?;
+        let mut candidate = self.heads[hash];
+        let mut best: Option<LcpBack> = None;
+
+        for _ in 0..self.depth {
+            // chains run from the nearest position backwards
+            if candidate == NONE || index - candidate > self.window {
+                break;
+            }
+
+            let lcp = self.data[candidate..]
+                .iter()
+                .zip(self.data[index..].iter())
+                .take(self.max_count)
+                .take_while(|(a, b)| a == b)
+                .count();
+
+            let longest = best.map_or(HASH_BYTES - 1, |best| best.lcp);
+            if lcp > longest {
+                best = Some(LcpBack {
+                    index: candidate,
+                    lcp,
+                });
+
+                if lcp >= self.max_count {
+                    break;
+                }
+            }
+
+            candidate = self.prevs[candidate];
+        }
+
+        best
+    }
+}
@@ -1,4 +1,10 @@
-use std::{marker::PhantomData, mem::size_of};
+use std::{
+    io::{Read, Write},
+    marker::PhantomData,
+    mem::size_of,
+};
+
+use crate::error::{Error, Result};
 
 pub trait HeadType {
     fn mark(byte_count: usize, buffer: &mut [u8]);
@@ -89,8 +95,8 @@ impl HeadType for LeadingZero {
     fn count(buffer: &[u8]) -> usize {
         let mut count: usize = 0;
 
-        for i in 0..buffer.len() {
-            let zeros = buffer[i].leading_zeros() as usize;
+        for byte in buffer {
+            let zeros = byte.leading_zeros() as usize;
             count += zeros;
 
             if zeros < 8 {
@@ -139,8 +145,8 @@ impl HeadType for LeadingOne {
     fn count(buffer: &[u8]) -> usize {
         let mut count: usize = 0;
 
-        for i in 0..buffer.len() {
-            let ones = buffer[i].leading_ones() as usize;
+        for byte in buffer {
+            let ones = byte.leading_ones() as usize;
             count += ones;
 
             if ones < 8 {
@@ -220,8 +226,8 @@ impl HeadType for Common {
     fn count(buffer: &[u8]) -> usize {
         let mut count: usize = 1;
 
-        for i in 0..buffer.len() {
-            let ones = buffer[i].leading_ones() as usize;
+        for byte in buffer {
+            let ones = byte.leading_ones() as usize;
             count += ones;
 
             if ones < 8 {
@@ -259,20 +265,62 @@ impl<H: HeadType> Head<H> {
         Default::default()
     }
 
-    pub fn extract(buffer: &[u8], data: &mut usize) -> usize {
+    pub fn extract(buffer: &[u8], data: &mut usize) -> Result<usize> {
         let head_size = H::count(buffer);
 
-        let mut head = vec![0u8; head_size];
-        head.copy_from_slice(&buffer[..head_size]);
+        if head_size == 0 || head_size > H::size(usize::MAX) {
+            return Err(Error::InvalidHead);
+        }
+
+        if head_size > buffer.len() {
+            return Err(Error::Truncated);
+        }
+
+        // the marker never reaches past the second byte
+        let mut marked = [buffer[0], buffer.get(1).copied().unwrap_or(0)];
+        H::mask(head_size, &mut marked[..head_size.min(2)]);
+
+        *data = (0..head_size)
+            .map(|index| {
+                if index < 2 {
+                    marked[index]
+                } else {
+                    buffer[index]
+                }
+            })
+            .fold(0, |value, byte| (value << 8) | byte as usize);
+
+        Ok(head_size)
+    }
 
-        H::mask(head_size, &mut head);
+    pub fn write(data: &usize, writer: &mut impl Write) -> std::io::Result<usize> {
+        let mut buffer = [0u8; 10];
+        let head_size = Self::compress(data, &mut buffer);
+        writer.write_all(&buffer[..head_size])?;
 
-        let mut bytes = [0u8; size_of::<usize>()];
-        let head = &head[(head.len().saturating_sub(size_of::<usize>()))..];
-        bytes[(size_of::<usize>() - head.len())..].copy_from_slice(head);
+        Ok(head_size)
+    }
 
-        *data = usize::from_be_bytes(bytes);
+    pub fn read(reader: &mut impl Read, data: &mut usize) -> Result<usize> {
+        let mut buffer = [0u8; 10];
+        let mut read_size = 1;
+        reader.read_exact(&mut buffer[..read_size])?;
 
-        head_size
+        // the marker may continue into the following bytes, so keep reading until it is complete
+        loop {
+            let head_size = H::count(&buffer[..read_size]).max(1);
+            if head_size <= read_size {
+                break;
+            }
+
+            if head_size > buffer.len() {
+                return Err(Error::InvalidHead);
+            }
+
+            reader.read_exact(&mut buffer[read_size..head_size])?;
+            read_size = head_size;
+        }
+
+        Self::extract(&buffer[..read_size], data)
     }
 }
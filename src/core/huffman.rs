@@ -0,0 +1,351 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use crate::error::{Error, Result};
+
+use super::bits::{BitReader, BitWriter};
+
+pub const MAX_CODE_BITS: usize = 15;
+
+const LENGTH_BITS: u32 = 4;
+const ZERO_RUN_BITS: u32 = 6;
+
+// Canonical Huffman code, fully described by the code length of every symbol.
+pub struct HuffmanTable {
+    lengths: Vec<u8>,
+    codes: Vec<u32>,
+}
+
+impl HuffmanTable {
+    // Unused symbols get no code, and no code is longer than `MAX_CODE_BITS`.
+    pub fn from_frequencies(frequencies: &[usize]) -> Self {
+        let mut frequencies = frequencies.to_vec();
+
+        loop {
+            let lengths = code_lengths(&frequencies);
+
+            if lengths
+                .iter()
+                .all(|&length| length as usize <= MAX_CODE_BITS)
+            {
+                return Self::from_lengths(lengths);
+            }
+
+            // flatten the distribution until the tree is shallow enough
+            for frequency in frequencies.iter_mut().filter(|frequency| **frequency > 0) {
+                *frequency = (*frequency >> 1) | 1;
+            }
+        }
+    }
+
+    fn from_lengths(lengths: Vec<u8>) -> Self {
+        let mut length_counts = [0u32; MAX_CODE_BITS + 1];
+        for &length in lengths.iter().filter(|&&length| length > 0) {
+            length_counts[length as usize] += 1;
+        }
+
+        let mut next_codes = [0u32; MAX_CODE_BITS + 1];
+        let mut code = 0;
+        for bits in 1..=MAX_CODE_BITS {
+            code = (code + length_counts[bits - 1]) << 1;
+            next_codes[bits] = code;
+        }
+
+        let codes = lengths
+            .iter()
+            .map(|&length| {
+                if length == 0 {
+                    return 0;
+                }
+
+                let code = next_codes[length as usize];
+                next_codes[length as usize] += 1;
+                code
+            })
+            .collect();
+
+        Self { lengths, codes }
+    }
+
+    pub fn lengths(&self) -> &[u8] {
+        &self.lengths
+    }
+
+    pub fn write_symbol(&self, symbol: usize, writer: &mut BitWriter) {
+        debug_assert!(self.lengths[symbol] > 0);
+        writer.write_code(self.codes[symbol], self.lengths[symbol] as u32);
+    }
+
+    // Code lengths as 4-bit values, where a 0 is followed by the length of its zero run.
+    pub fn write_lengths(&self, writer: &mut BitWriter) {
+        let mut index = 0;
+
+        while index < self.lengths.len() {
+            let length = self.lengths[index];
+
+            if length > 0 {
+                writer.write(length as u64, LENGTH_BITS);
+                index += 1;
+                continue;
+            }
+
+            let run = self.lengths[index..]
+                .iter()
+                .take(1 << ZERO_RUN_BITS)
+                .take_while(|&&length| length == 0)
+                .count();
+
+            writer.write(0, LENGTH_BITS);
+            writer.write((run - 1) as u64, ZERO_RUN_BITS);
+            index += run;
+        }
+    }
+}
+
+// Decodes symbols bit by bit, walking the canonical codes one length at a time.
+pub struct HuffmanDecoder {
+    length_counts: [usize; MAX_CODE_BITS + 1],
+    symbols: Vec<usize>,
+}
+
+impl HuffmanDecoder {
+    pub fn from_lengths(lengths: &[u8]) -> Result<Self> {
+        let mut length_counts = [0usize; MAX_CODE_BITS + 1];
+        for &length in lengths.iter() {
+            if length as usize > MAX_CODE_BITS {
+                return Err(Error::InvalidCode);
+            }
+
+            length_counts[length as usize] += 1;
+        }
+        length_counts[0] = 0;
+
+        // more codes of some length than the shorter ones leave room for
+        let mut left = 1usize;
+        for &count in length_counts.iter().skip(1) {
+            left <<= 1;
+            left = left.checked_sub(count).ok_or(Error::InvalidCode)?;
+        }
+
+        // codes leaving room for more, except the lone 1-bit code of a single used symbol
+        // and the empty table of unused ones
+        let used = length_counts.iter().sum::<usize>();
+        if left > 0 && used > 0 && !(used == 1 && length_counts[1] == 1) {
+            return Err(Error::InvalidCode);
+        }
+
+        let mut symbols: Vec<usize> = (0..lengths.len())
+            .filter(|&symbol| lengths[symbol] > 0)
+            .collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol]);
+
+        Ok(Self {
+            length_counts,
+            symbols,
+        })
+    }
+
+    pub fn read_lengths(reader: &mut BitReader, symbol_count: usize) -> Result<Self> {
+        let mut lengths = Vec::with_capacity(symbol_count);
+
+        while lengths.len() < symbol_count {
+            let length = reader.read(LENGTH_BITS)? as u8;
+
+            if length > 0 {
+                lengths.push(length);
+                continue;
+            }
+
+            let run = reader.read(ZERO_RUN_BITS)? as usize + 1;
+            if run > symbol_count - lengths.len() {
+                return Err(Error::InvalidCode);
+            }
+
+            lengths.resize(lengths.len() + run, 0);
+        }
+
+        Self::from_lengths(&lengths)
+    }
+
+    pub fn read_symbol(&self, reader: &mut BitReader) -> Result<usize> {
+        let mut code = 0;
+        let mut first = 0;
+        let mut index = 0;
+
+        for &count in self.length_counts.iter().skip(1) {
+            code |= reader.read_bit()? as usize;
+
+            if code < first + count {
+                return Ok(self.symbols[index + code - first]);
+            }
+
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(Error::InvalidCode)
+    }
+}
+
+// Depth of every symbol in a Huffman tree over the used symbols, without any depth limit.
+fn code_lengths(frequencies: &[usize]) -> Vec<u8> {
+    let mut lengths = vec![0u8; frequencies.len()];
+    let used: Vec<usize> = (0..frequencies.len())
+        .filter(|&symbol| frequencies[symbol] > 0)
+        .collect();
+
+    match used[..] {
+        [] => return lengths,
+        [symbol] => {
+            lengths[symbol] = 1;
+            return lengths;
+        }
+        _ => {}
+    }
+
+    // leaves come first, then every merged node after both of its children
+    let mut parents = vec![0usize; used.len()];
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = used
+        .iter()
+        .enumerate()
+        .map(|(node, &symbol)| Reverse((frequencies[symbol], node)))
+        .collect();
+
+    while let (Some(Reverse((a, a_node))), Some(Reverse((b, b_node)))) = (heap.pop(), heap.pop()) {
+        let node = parents.len();
+        parents.push(node);
+        parents[a_node] = node;
+        parents[b_node] = node;
+        heap.push(Reverse((a + b, node)));
+    }
+
+    let mut depths = vec![0u8; parents.len()];
+    for node in (0..(parents.len() - 1)).rev() {
+        depths[node] = depths[parents[node]].saturating_add(1);
+    }
+
+    for (node, &symbol) in used.iter().enumerate() {
+        lengths[symbol] = depths[node];
+    }
+
+    lengths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    fn roundtrip(frequencies: &[usize], symbols: &[usize]) -> HuffmanTable {
+        let table = HuffmanTable::from_frequencies(frequencies);
+        assert!(table
+            .lengths()
+            .iter()
+            .all(|&length| length as usize <= MAX_CODE_BITS));
+
+        let mut bits = BitWriter::new();
+        table.write_lengths(&mut bits);
+        for &symbol in symbols.iter() {
+            table.write_symbol(symbol, &mut bits);
+        }
+        let buffer = bits.finish();
+
+        let mut reader = BitReader::new(&buffer);
+        let decoder = HuffmanDecoder::read_lengths(&mut reader, frequencies.len()).unwrap();
+        for &symbol in symbols.iter() {
+            assert_eq!(decoder.read_symbol(&mut reader).unwrap(), symbol);
+        }
+
+        table
+    }
+
+    fn read_lengths(lengths: &[u8]) -> Result<HuffmanDecoder> {
+        let mut bits = BitWriter::new();
+        for &length in lengths.iter() {
+            if length > 0 {
+                bits.write(length as u64, LENGTH_BITS);
+            } else {
+                bits.write(0, LENGTH_BITS);
+                bits.write(0, ZERO_RUN_BITS);
+            }
+        }
+        let buffer = bits.finish();
+
+        HuffmanDecoder::read_lengths(&mut BitReader::new(&buffer), lengths.len())
+    }
+
+    #[test]
+    fn random_roundtrips() {
+        let mut random = Random(1);
+        for _ in 0..200 {
+            let symbol_count = 1 + random.next() as usize % 300;
+            let frequencies: Vec<usize> = (0..symbol_count)
+                .map(|_| match random.next() % 3 {
+                    0 => 0,
+                    _ => random.next() as usize % 1000,
+                })
+                .collect();
+            let used: Vec<usize> = (0..symbol_count)
+                .filter(|&symbol| frequencies[symbol] > 0)
+                .collect();
+            let symbols: Vec<usize> = (0..used.len() * 4)
+                .map(|_| used[random.next() as usize % used.len()])
+                .collect();
+
+            roundtrip(&frequencies, &symbols);
+        }
+    }
+
+    #[test]
+    fn no_used_symbols() {
+        let table = roundtrip(&[0; 30], &[]);
+        assert!(table.lengths().iter().all(|&length| length == 0));
+    }
+
+    #[test]
+    fn single_used_symbol() {
+        let mut frequencies = vec![0; 30];
+        frequencies[7] = 5;
+        let table = roundtrip(&frequencies, &[7, 7, 7]);
+        assert_eq!(table.lengths()[7], 1);
+    }
+
+    #[test]
+    fn skewed_frequencies_are_flattened() {
+        // Fibonacci frequencies make the deepest Huffman tree
+        let mut frequencies = vec![1usize, 1];
+        while frequencies.len() < 40 {
+            frequencies
+                .push(frequencies[frequencies.len() - 1] + frequencies[frequencies.len() - 2]);
+        }
+        assert!(code_lengths(&frequencies)
+            .iter()
+            .any(|&length| length as usize > MAX_CODE_BITS));
+
+        let symbols: Vec<usize> = (0..frequencies.len()).collect();
+        let table = roundtrip(&frequencies, &symbols);
+        assert_eq!(
+            *table.lengths().iter().max().unwrap() as usize,
+            MAX_CODE_BITS
+        );
+    }
+
+    #[test]
+    fn rejects_oversubscribed_codes() {
+        assert!(read_lengths(&[1, 1, 1]).is_err());
+        assert!(read_lengths(&[1, 2, 2, 2]).is_err());
+        assert!(read_lengths(&[2, 2, 2, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn rejects_undersubscribed_codes() {
+        assert!(read_lengths(&[1, 2]).is_err());
+        assert!(read_lengths(&[2, 2, 2, 0]).is_err());
+        assert!(read_lengths(&[1, 3, 3, 0, 3]).is_err());
+        assert!(read_lengths(&[2, 0, 0]).is_err());
+
+        assert!(read_lengths(&[1, 2, 2]).is_ok());
+        assert!(read_lengths(&[0, 1, 0]).is_ok());
+        assert!(read_lengths(&[0, 0, 0]).is_ok());
+    }
+}
@@ -1,16 +1,84 @@
-use std::io::Write;
+use std::{collections::VecDeque, io::Write};
+
+use crate::error::{Error, Result};
 
 use super::{
+    hash_chain::HashChain,
+    head::{Head, LeadingOne},
     match_layout::{Match, MatchLayout},
     packed_bits::PackedBits,
-    suffix_array::U8Bucket,
-    suffix_reference::SuffixReference,
+    suffix_array::{SuffixIndex, U8Bucket},
+    suffix_reference::{LcpBack, SuffixReference},
 };
 
 pub struct LZ77;
 
+// Output reserved up front per byte of compressed input, at most the expected size.
+const PREALLOCATE_RATIO: usize = 16;
+// Matches are copied in chunks of this size, possibly writing past their end.
+const WILD_COPY: usize = 16;
+// Every match length up to this is tried by the optimal parse, longer ones only at their full
+// length and at head size boundaries.
+const SHORT_COUNT: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parsing {
+    // Greedy parse over a hash chain examining `depth` candidates, without a suffix array.
+    Fast { depth: usize },
+    // Takes the longest match at each position.
+    Greedy,
+    // Defers a match by one byte when the next position has a more profitable one.
+    // Matches of at least `nice_count` bytes are taken immediately.
+    Lazy { nice_count: usize },
+    // Shortest path over encoded sizes, over the `candidate_count` longest of the nearest
+    // occurrences for each match length. Every candidate takes about four times a lazy parse.
+    Optimal { candidate_count: usize },
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CompressOptions {
+    pub parsing: Parsing,
+    // Largest `Match::left` the parser may emit, so extraction only needs that much history.
+    pub window: Option<usize>,
+}
+
+impl CompressOptions {
+    pub const MIN_LEVEL: u8 = 1;
+    pub const MAX_LEVEL: u8 = 9;
+    pub const DEFAULT_LEVEL: u8 = 6;
+
+    pub fn with_level(level: u8) -> Self {
+        let parsing = match level.clamp(Self::MIN_LEVEL, Self::MAX_LEVEL) {
+            1 => Parsing::Fast { depth: 1 },
+            2 => Parsing::Fast { depth: 4 },
+            3 => Parsing::Fast { depth: 8 },
+            4 => Parsing::Greedy,
+            5 => Parsing::Lazy { nice_count: 16 },
+            6 => Parsing::Lazy {
+                nice_count: usize::MAX,
+            },
+            7 => Parsing::Optimal { candidate_count: 1 },
+            8 => Parsing::Optimal { candidate_count: 2 },
+            _ => Parsing::Optimal {
+                candidate_count: usize::MAX,
+            },
+        };
+
+        Self {
+            parsing,
+            window: None,
+        }
+    }
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        Self::with_level(Self::DEFAULT_LEVEL)
+    }
+}
+
 #[derive(Debug)]
-enum CompressMode {
+pub enum CompressMode {
     Match {
         buffer_size: usize,
         to: usize,
@@ -23,67 +91,49 @@ enum CompressMode {
 }
 
 impl LZ77 {
-    pub fn compress(data: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
-        let suffix = SuffixReference::from_data(data, &U8Bucket);
-        let modes = {
-            let mut modes = vec![];
-            let mut head = 0;
-            let mut index = 0;
-
-            while index < data.len() {
-                if let Some(back) = suffix.back(index) {
-                    let match_ = Match {
-                        left: index - back.index,
-                        count: back.lcp,
-                    };
-                    let m_stop = index + match_.count;
+    // Splits `data[start..]` into packed runs and matches, as `compress` encodes them.
+    // Matches may reach back into `data[..start]`, which the extractor is given as history.
+    pub fn parse(data: &[u8], start: usize, options: &CompressOptions) -> Vec<CompressMode> {
+        let window = options.window.unwrap_or(usize::MAX);
 
-                    let m_size = MatchLayout::measure(&match_);
-                    let mp_size = if head < index {
-                        PackedBits::measure(&data[head..index])
-                    } else {
-                        0
-                    };
-                    // let p_size = PackedBits::measure(&data[head..m_stop]);
-                    let p_size = m_stop - head;
-
-                    if m_size + mp_size < p_size {
-                        if head < index {
-                            modes.push(CompressMode::Pack {
-                                buffer_size: mp_size,
-                                to: index,
-                            });
-                        }
-
-                        modes.push(CompressMode::Match {
-                            buffer_size: m_size,
-                            to: m_stop,
-                            match_,
-                        });
-
-                        head = m_stop;
-                        index = m_stop;
-                    } else {
-                        index += 1;
-                    }
-                } else {
-                    index += 1;
-                }
-            }
+        if let Parsing::Fast { depth } = options.parsing {
+            let mut chain = HashChain::new(data, depth, window, usize::MAX);
+            parse_greedy(data, start, 0, |index| chain.back(index))
+        } else if data.len() < u32::MAX as usize {
+            Self::parse_suffix::<u32>(data, start, options.parsing, window)
+        } else {
+            Self::parse_suffix::<u64>(data, start, options.parsing, window)
+        }
+    }
 
-            if head < data.len() {
-                let p_size = PackedBits::measure(&data[head..]);
-                modes.push(CompressMode::Pack {
-                    buffer_size: p_size,
-                    to: data.len(),
-                });
-            }
+    fn parse_suffix<I: SuffixIndex>(
+        data: &[u8],
+        start: usize,
+        parsing: Parsing,
+        window: usize,
+    ) -> Vec<CompressMode> {
+        let suffix = SuffixReference::<_, I>::from_data(data, &U8Bucket);
+        let mut search = suffix.window_search(window);
+        let back = |index| search.back(index);
 
-            modes
-        };
+        match parsing {
+            Parsing::Fast { .. } | Parsing::Greedy => parse_greedy(data, start, 0, back),
+            Parsing::Lazy { nice_count } => parse_greedy(data, start, nice_count, back),
+            Parsing::Optimal { candidate_count } => parse_optimal(data, start, |index| {
+                search.candidates(index, candidate_count)
+            }),
+        }
+    }
 
-        let mut cursor = 0;
-        for mode in modes {
+    // Encodes `data[start..]`, as `parse` splits it.
+    pub fn compress(
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut cursor = start;
+        for mode in Self::parse(data, start, options) {
             let buffer = match mode {
                 CompressMode::Match {
                     buffer_size,
@@ -109,47 +159,87 @@ impl LZ77 {
         Ok(())
     }
 
-    pub fn extract(buffer: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
-        let mut memory = vec![];
+    // Fails on matches reaching further back than `window`, even within the extracted data.
+    // Matches may reach back into `history`, the data preceding the extracted one.
+    // The output is built in a single buffer and written out at once.
+    pub fn extract(
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        let capacity = max_size.min(buffer.len().saturating_mul(PREALLOCATE_RATIO));
+        let mut output = Output::new(history, capacity);
         let mut head = 0;
 
         while head < buffer.len() {
             let buffer = &buffer[head..];
 
-            if MatchLayout::check(&buffer) {
+            if MatchLayout::check(buffer) {
                 let mut match_ = MatchLayout::prepare(buffer);
-                let read_size = MatchLayout::extract(buffer, &mut match_);
-
-                let cursor = memory.len();
-                let back_start = cursor - match_.left;
-
-                for i in 0..match_.count {
-                    memory.push(memory[back_start + i]);
-                }
+                head += MatchLayout::extract(buffer, &mut match_)?;
 
-                writer.write_all(&memory[cursor..])?;
-                head += read_size;
+                output.copy_match(&match_, max_size, window)?;
             } else {
-                let mut data = PackedBits::prepare(buffer);
-                let read_size = PackedBits::extract(buffer, &mut data);
-                memory.extend_from_slice(&data);
-                writer.write_all(&data)?;
+                let (data, read_size) = PackedBits::extract_slice(buffer)?;
                 head += read_size;
+
+                output.copy_literals(data, max_size)?;
             }
         }
 
+        writer.write_all(output.as_slice())?;
+        Ok(output.as_slice().len())
+    }
+
+    // Appends the bytes `match_` refers to, checking it against the window and `max_size`.
+    // `memory` starts with `start` bytes of history, which do not count towards `max_size`.
+    pub fn copy_match(
+        memory: &mut Vec<u8>,
+        start: usize,
+        match_: &Match,
+        max_size: usize,
+        window: usize,
+    ) -> Result<()> {
+        let cursor = memory.len();
+        if match_.left == 0 || match_.left > cursor.min(window) {
+            return Err(Error::OffsetBeyondWindow {
+                left: match_.left,
+                window: cursor.min(window),
+            });
+        }
+        let back_start = cursor - match_.left;
+
+        let extracted_size = cursor - start;
+        if match_.count > max_size - extracted_size {
+            return Err(Error::SizeMismatch {
+                expected: max_size,
+                actual: extracted_size.saturating_add(match_.count),
+            });
+        }
+
+        // an overlapping match repeats its first `left` bytes, and everything copied so far
+        // is a whole number of repetitions, so the copies can double in size
+        let mut remaining = match_.count;
+        while remaining > 0 {
+            let size = remaining.min(memory.len() - back_start);
+            memory.extend_from_within(back_start..(back_start + size));
+            remaining -= size;
+        }
+
         Ok(())
     }
 
-    pub fn dump(buffer: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
+    pub fn dump(buffer: &[u8], writer: &mut impl Write) -> Result<()> {
         let mut head = 0;
 
         while head < buffer.len() {
             let buffer = &buffer[head..];
 
-            if MatchLayout::check(&buffer) {
+            if MatchLayout::check(buffer) {
                 let mut match_ = MatchLayout::prepare(buffer);
-                let read_size = MatchLayout::extract(buffer, &mut match_);
+                let read_size = MatchLayout::extract(buffer, &mut match_)?;
 
                 writeln!(
                     writer,
@@ -159,8 +249,8 @@ impl LZ77 {
 
                 head += read_size;
             } else {
-                let mut data = PackedBits::prepare(buffer);
-                let read_size = PackedBits::extract(buffer, &mut data);
+                let mut data = PackedBits::prepare(buffer)?;
+                let read_size = PackedBits::extract(buffer, &mut data)?;
 
                 writeln!(
                     writer,
@@ -177,3 +267,473 @@ impl LZ77 {
         Ok(())
     }
 }
+
+// History and extracted bytes followed by enough slack for chunked copies to overrun
+// the last match.
+struct Output {
+    buffer: Vec<u8>,
+    start: usize,
+    size: usize,
+}
+
+impl Output {
+    fn new(history: &[u8], capacity: usize) -> Self {
+        let mut buffer = Vec::with_capacity(history.len() + capacity + WILD_COPY);
+        buffer.extend_from_slice(history);
+        buffer.resize(history.len() + capacity + WILD_COPY, 0);
+
+        Self {
+            buffer,
+            start: history.len(),
+            size: history.len(),
+        }
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        &self.buffer[self.start..self.size]
+    }
+
+    fn reserve(&mut self, count: usize, max_size: usize) -> Result<()> {
+        let extracted_size = self.size - self.start;
+        if count > max_size - extracted_size {
+            return Err(Error::SizeMismatch {
+                expected: max_size,
+                actual: extracted_size.saturating_add(count),
+            });
+        }
+
+        let required = (self.size + count).saturating_add(WILD_COPY);
+        if required > self.buffer.len() {
+            self.buffer.resize(required.max(self.buffer.len() * 2), 0);
+        }
+
+        Ok(())
+    }
+
+    fn copy_literals(&mut self, data: &[u8], max_size: usize) -> Result<()> {
+        self.reserve(data.len(), max_size)?;
+
+        self.buffer[self.size..(self.size + data.len())].copy_from_slice(data);
+        self.size += data.len();
+
+        Ok(())
+    }
+
+    fn copy_match(&mut self, match_: &Match, max_size: usize, window: usize) -> Result<()> {
+        if match_.left == 0 || match_.left > self.size.min(window) {
+            return Err(Error::OffsetBeyondWindow {
+                left: match_.left,
+                window: self.size.min(window),
+            });
+        }
+
+        self.reserve(match_.count, max_size)?;
+
+        let start = self.size - match_.left;
+        let end = self.size + match_.count;
+
+        if match_.left >= WILD_COPY {
+            // every chunk reads only bytes written before it
+            for to in (self.size..end).step_by(WILD_COPY) {
+                let from = to - match_.left;
+                self.buffer.copy_within(from..(from + WILD_COPY), to);
+            }
+        } else {
+            // the first `left` bytes repeat, so the copies can double in size
+            let mut to = self.size;
+            while to < end {
+                let size = (end - to).min(to - start);
+                self.buffer.copy_within(start..(start + size), to);
+                to += size;
+            }
+        }
+
+        self.size = end;
+        Ok(())
+    }
+}
+
+// Profit of a match over packing the same bytes.
+fn match_gain(match_: &Match) -> isize {
+    match_.count as isize - MatchLayout::measure(match_) as isize
+}
+
+// Takes the longest match from `back` whenever it is smaller than packing, from `start` on.
+// With a positive `lazy_count`, a match shorter than it is deferred by one byte
+// when the next position has a match that gains more than the extra packed byte.
+fn parse_greedy(
+    data: &[u8],
+    start: usize,
+    lazy_count: usize,
+    mut back: impl FnMut(usize) -> Option<LcpBack>,
+) -> Vec<CompressMode> {
+    let mut modes = vec![];
+    let mut head = start;
+    let mut index = start;
+
+    while index < data.len() {
+        if let Some(back_) = back(index) {
+            let match_ = Match {
+                left: index - back_.index,
+                count: back_.lcp,
+            };
+            let m_stop = index + match_.count;
+
+            let m_size = MatchLayout::measure(&match_);
+            let mp_size = if head < index {
+                PackedBits::measure(&data[head..index])
+            } else {
+                0
+            };
+            let p_size = m_stop - head;
+
+            let deferred = match_.count < lazy_count
+                && back(index + 1).is_some_and(|next| {
+                    let next_match = Match {
+                        left: index + 1 - next.index,
+                        count: next.lcp,
+                    };
+                    match_gain(&next_match) > match_gain(&match_) + 1
+                });
+
+            if !deferred && m_size + mp_size < p_size {
+                if head < index {
+                    modes.push(CompressMode::Pack {
+                        buffer_size: mp_size,
+                        to: index,
+                    });
+                }
+
+                modes.push(CompressMode::Match {
+                    buffer_size: m_size,
+                    to: m_stop,
+                    match_,
+                });
+
+                head = m_stop;
+                index = m_stop;
+            } else {
+                index += 1;
+            }
+        } else {
+            index += 1;
+        }
+    }
+
+    if head < data.len() {
+        let p_size = PackedBits::measure(&data[head..]);
+        modes.push(CompressMode::Pack {
+            buffer_size: p_size,
+            to: data.len(),
+        });
+    }
+
+    modes
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Pack { from: usize },
+    Match { from: usize, left: usize },
+}
+
+// Largest count encodable with each head size, in increasing order.
+// `PackedBits` and `MatchLayout` counts share the same boundaries.
+fn count_tiers(max_count: usize) -> Vec<(usize, usize)> {
+    let mut tiers: Vec<(usize, usize)> = vec![];
+
+    for bits in 1..usize::BITS {
+        let count = (1usize << bits) - 1;
+        let head_size = Head::<LeadingOne>::measure(&count);
+
+        match tiers.last_mut() {
+            Some(tier) if tier.0 == head_size => tier.1 = count,
+            _ => tiers.push((head_size, count)),
+        }
+
+        if count >= max_count {
+            break;
+        }
+    }
+
+    tiers
+}
+
+// Shortest path over encoded sizes, where `costs[index]` is the smallest size of
+// `data[start..index]`. Match lengths are tried with the nearest of `candidates` reaching that far.
+fn parse_optimal(
+    data: &[u8],
+    start: usize,
+    mut candidates: impl FnMut(usize) -> Vec<LcpBack>,
+) -> Vec<CompressMode> {
+    let tiers = count_tiers(data.len() - start);
+
+    let mut costs = vec![usize::MAX; data.len() + 1];
+    let mut steps = vec![Step::Pack { from: start }; data.len() + 1];
+    costs[start] = 0;
+
+    // candidate starts of a pack ending at the current index, one window per head size
+    let mut windows: Vec<VecDeque<usize>> = vec![VecDeque::new(); tiers.len()];
+    let mut counts = vec![];
+
+    for index in start..=data.len() {
+        for (&(head_size, max_count), window) in tiers.iter().zip(windows.iter_mut()) {
+            while window.front().is_some_and(|&from| index - from > max_count) {
+                window.pop_front();
+            }
+
+            if let Some(&from) = window.front() {
+                let cost = costs[from] + head_size + (index - from);
+
+                if cost < costs[index] {
+                    costs[index] = cost;
+                    steps[index] = Step::Pack { from };
+                }
+            }
+        }
+
+        if index >= data.len() {
+            break;
+        }
+
+        // keep each window ordered by the cost of packing from there onwards
+        for window in windows.iter_mut() {
+            while window
+                .back()
+                .is_some_and(|&from| costs[from] + index >= costs[index] + from)
+            {
+                window.pop_back();
+            }
+
+            window.push_back(index);
+        }
+
+        // shortest candidates first, each one the nearest from the previous one's length onwards
+        counts.clear();
+        let mut min_count = 2;
+
+        for back in candidates(index).into_iter().rev() {
+            let left = index - back.index;
+            let max_count = back.lcp;
+
+            counts.extend((min_count..=max_count.min(SHORT_COUNT)).map(|count| (count, left)));
+            counts.extend(
+                tiers
+                    .iter()
+                    .map(|&(_, tier_count)| tier_count)
+                    .filter(|&count| min_count.max(SHORT_COUNT + 1) <= count && count < max_count)
+                    .map(|count| (count, left)),
+            );
+            if max_count > SHORT_COUNT && max_count >= min_count {
+                counts.push((max_count, left));
+            }
+
+            min_count = min_count.max(max_count + 1);
+        }
+
+        for &(count, left) in counts.iter() {
+            let cost = costs[index] + MatchLayout::measure(&Match { left, count });
+
+            if cost < costs[index + count] {
+                costs[index + count] = cost;
+                steps[index + count] = Step::Match { from: index, left };
+            }
+        }
+    }
+
+    let mut modes = vec![];
+    let mut to = data.len();
+
+    while to > start {
+        match steps[to] {
+            Step::Pack { from } => {
+                modes.push(CompressMode::Pack {
+                    buffer_size: PackedBits::measure(&data[from..to]),
+                    to,
+                });
+                to = from;
+            }
+            Step::Match { from, left } => {
+                let match_ = Match {
+                    left,
+                    count: to - from,
+                };
+                modes.push(CompressMode::Match {
+                    buffer_size: MatchLayout::measure(&match_),
+                    to,
+                    match_,
+                });
+                to = from;
+            }
+        }
+    }
+
+    modes.reverse();
+    modes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::{fibonacci, runs, Random};
+
+    const WINDOWS: [Option<usize>; 6] = [Some(1), Some(2), Some(7), Some(64), Some(4096), None];
+
+    fn compressed_size(data: &[u8], options: &CompressOptions) -> usize {
+        let mut compressed = vec![];
+        LZ77::compress(data, 0, options, &mut compressed).unwrap();
+        compressed.len()
+    }
+
+    // Optimal parses extract back to `data` and are never larger than greedy ones.
+    fn check_optimal(data: &[u8]) {
+        for window in WINDOWS {
+            let greedy = CompressOptions {
+                parsing: Parsing::Greedy,
+                window,
+            };
+            let greedy_size = compressed_size(data, &greedy);
+
+            for candidate_count in [1, 2, usize::MAX] {
+                let options = CompressOptions {
+                    parsing: Parsing::Optimal { candidate_count },
+                    window,
+                };
+                let mut compressed = vec![];
+                LZ77::compress(data, 0, &options, &mut compressed).unwrap();
+
+                let mut extracted = vec![];
+                LZ77::extract(
+                    &compressed,
+                    &[],
+                    data.len(),
+                    window.unwrap_or(usize::MAX),
+                    &mut extracted,
+                )
+                .unwrap();
+                assert_eq!(extracted, data, "{:?} {:?}", window, candidate_count);
+                assert!(
+                    compressed.len() <= greedy_size,
+                    "{:?} {:?}: {} > {}",
+                    window,
+                    candidate_count,
+                    compressed.len(),
+                    greedy_size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn optimal_random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..100 {
+            check_optimal(&random.bytes(1000));
+        }
+    }
+
+    #[test]
+    fn optimal_runs_of_one_byte() {
+        for data in runs() {
+            check_optimal(&data);
+        }
+        check_optimal(&vec![7u8; 20000]);
+    }
+
+    #[test]
+    fn optimal_fibonacci_strings() {
+        for data in fibonacci(5000) {
+            check_optimal(&data);
+        }
+    }
+
+    #[test]
+    fn count_tiers_follow_head_sizes() {
+        let tiers = count_tiers(u32::MAX as usize);
+        let mut min_count = 1;
+
+        for &(head_size, max_count) in tiers.iter() {
+            assert_eq!(Head::<LeadingOne>::measure(&min_count), head_size);
+            assert_eq!(Head::<LeadingOne>::measure(&max_count), head_size);
+            min_count = max_count + 1;
+        }
+        for window in tiers.windows(2) {
+            assert!(window[0].0 < window[1].0);
+        }
+        assert!(count_tiers(100).last().unwrap().1 >= 100);
+    }
+
+    #[test]
+    fn matches_reach_into_history() {
+        let mut random = Random(3);
+        let text: Vec<u8> = (0..2000).map(|_| random.next() as u8).collect();
+        let repeated = text.repeat(2);
+        let fibonacci = fibonacci(5000).pop().unwrap();
+
+        for level in CompressOptions::MIN_LEVEL..=CompressOptions::MAX_LEVEL {
+            for window in [None, Some(100)] {
+                let options = CompressOptions {
+                    window,
+                    ..CompressOptions::with_level(level)
+                };
+
+                for data in [&repeated, &fibonacci] {
+                    for start in [0, 1, 1000, 2000, data.len() - 1, data.len()] {
+                        let mut compressed = vec![];
+                        LZ77::compress(data, start, &options, &mut compressed).unwrap();
+
+                        let mut extracted = vec![];
+                        LZ77::extract(
+                            &compressed,
+                            &data[..start],
+                            data.len() - start,
+                            window.unwrap_or(usize::MAX),
+                            &mut extracted,
+                        )
+                        .unwrap();
+                        assert_eq!(extracted, data[start..], "{} {:?} {}", level, window, start);
+                    }
+                }
+
+                // the repetition is a single match back into the history
+                let mut compressed = vec![];
+                LZ77::compress(&repeated, text.len(), &options, &mut compressed).unwrap();
+                if window.is_none() {
+                    assert!(compressed.len() < 10, "{} {}", level, compressed.len());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn higher_levels_are_never_larger() {
+        // Levels up to 6 are heuristics that can swap by a few bytes on some inputs, and fast
+        // levels beat level 4 on inputs repeating mostly short, near strings. Levels 7 to 9 never
+        // grow, searching a superset of the matches of the level below.
+        let data = [
+            &include_bytes!("bwt.rs")[..],
+            include_bytes!("head.rs"),
+            include_bytes!("range_coder.rs"),
+            include_bytes!("lz77_range.rs"),
+        ]
+        .concat();
+
+        for window in [None, Some(4096)] {
+            let sizes: Vec<usize> = (CompressOptions::MIN_LEVEL..=CompressOptions::MAX_LEVEL)
+                .map(|level| {
+                    let options = CompressOptions {
+                        window,
+                        ..CompressOptions::with_level(level)
+                    };
+                    compressed_size(&data, &options)
+                })
+                .collect();
+
+            assert!(
+                sizes.windows(2).all(|pair| pair[1] <= pair[0]),
+                "{:?}",
+                sizes
+            );
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use std::io::Write;
+
+use crate::error::{Error, Result};
+
+use super::{
+    bits::{BitReader, BitWriter},
+    bucket::{self, BUCKET_COUNT},
+    huffman::{HuffmanDecoder, HuffmanTable},
+    lz77::{CompressMode, CompressOptions, LZ77},
+    match_layout::Match,
+};
+
+// Literal bytes, the end of the block, then match length buckets share one table as in deflate.
+const END_SYMBOL: usize = 256;
+const LENGTH_SYMBOL: usize = END_SYMBOL + 1;
+const LITERAL_COUNT: usize = LENGTH_SYMBOL + BUCKET_COUNT;
+
+// The LZ77 token stream with literals, match lengths and offset buckets Huffman coded.
+// Every block starts with the code lengths of its own literal/length and offset tables.
+pub struct LZ77Huffman;
+
+enum Token {
+    Literal(u8),
+    Match(Match),
+}
+
+impl LZ77Huffman {
+    // Encodes `data[start..]`, with matches reaching back into `data[..start]` as in `LZ77`.
+    pub fn compress(
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut tokens = vec![];
+        let mut cursor = start;
+
+        for mode in LZ77::parse(data, start, options) {
+            match mode {
+                CompressMode::Pack { to, .. } => {
+                    tokens.extend(data[cursor..to].iter().map(|&byte| Token::Literal(byte)));
+                    cursor = to;
+                }
+                CompressMode::Match { to, match_, .. } => {
+                    tokens.push(Token::Match(match_));
+                    cursor = to;
+                }
+            }
+        }
+
+        let mut literal_frequencies = vec![0usize; LITERAL_COUNT];
+        let mut offset_frequencies = vec![0usize; BUCKET_COUNT];
+        literal_frequencies[END_SYMBOL] += 1;
+
+        for token in tokens.iter() {
+            match token {
+                Token::Literal(byte) => literal_frequencies[*byte as usize] += 1,
+                Token::Match(match_) => {
+                    literal_frequencies[LENGTH_SYMBOL + bucket::split(match_.count - 1).0] += 1;
+                    offset_frequencies[bucket::split(match_.left - 1).0] += 1;
+                }
+            }
+        }
+
+        let literal_table = HuffmanTable::from_frequencies(&literal_frequencies);
+        let offset_table = HuffmanTable::from_frequencies(&offset_frequencies);
+
+        let mut bits = BitWriter::new();
+        literal_table.write_lengths(&mut bits);
+        offset_table.write_lengths(&mut bits);
+
+        for token in tokens.iter() {
+            match token {
+                Token::Literal(byte) => literal_table.write_symbol(*byte as usize, &mut bits),
+                Token::Match(match_) => {
+                    let (symbol, extra_bits, extra) = bucket::split(match_.count - 1);
+                    literal_table.write_symbol(LENGTH_SYMBOL + symbol, &mut bits);
+                    bits.write(extra, extra_bits);
+
+                    let (symbol, extra_bits, extra) = bucket::split(match_.left - 1);
+                    offset_table.write_symbol(symbol, &mut bits);
+                    bits.write(extra, extra_bits);
+                }
+            }
+        }
+
+        literal_table.write_symbol(END_SYMBOL, &mut bits);
+        writer.write_all(&bits.finish())
+    }
+
+    pub fn extract(
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        let mut memory = history.to_vec();
+        let mut bits = BitReader::new(buffer);
+
+        let literal_decoder = HuffmanDecoder::read_lengths(&mut bits, LITERAL_COUNT)?;
+        let offset_decoder = HuffmanDecoder::read_lengths(&mut bits, BUCKET_COUNT)?;
+
+        loop {
+            let symbol = literal_decoder.read_symbol(&mut bits)?;
+
+            if symbol < END_SYMBOL {
+                if memory.len() - history.len() >= max_size {
+                    return Err(Error::SizeMismatch {
+                        expected: max_size,
+                        actual: memory.len() - history.len() + 1,
+                    });
+                }
+
+                memory.push(symbol as u8);
+            } else if symbol == END_SYMBOL {
+                break;
+            } else {
+                let count = read_bucket(symbol - LENGTH_SYMBOL, &mut bits)?.saturating_add(1);
+                let symbol = offset_decoder.read_symbol(&mut bits)?;
+                let left = read_bucket(symbol, &mut bits)?.saturating_add(1);
+
+                let match_ = Match { left, count };
+                LZ77::copy_match(&mut memory, history.len(), &match_, max_size, window)?;
+            }
+        }
+
+        writer.write_all(&memory[history.len()..])?;
+        Ok(memory.len() - history.len())
+    }
+
+    pub fn dump(buffer: &[u8], writer: &mut impl Write) -> Result<()> {
+        let mut bits = BitReader::new(buffer);
+
+        let literal_decoder = HuffmanDecoder::read_lengths(&mut bits, LITERAL_COUNT)?;
+        let offset_decoder = HuffmanDecoder::read_lengths(&mut bits, BUCKET_COUNT)?;
+        writeln!(writer, "{:>8x}: tables", bits.position())?;
+
+        let mut cursor = 0usize;
+        let mut literal_count = 0;
+
+        loop {
+            let symbol = literal_decoder.read_symbol(&mut bits)?;
+
+            if symbol < END_SYMBOL {
+                literal_count += 1;
+                continue;
+            }
+
+            if literal_count > 0 {
+                writeln!(writer, "{:>8x}: P count: {:x}", cursor, literal_count)?;
+                cursor += literal_count;
+                literal_count = 0;
+            }
+
+            if symbol == END_SYMBOL {
+                break;
+            }
+
+            let count = read_bucket(symbol - LENGTH_SYMBOL, &mut bits)?.saturating_add(1);
+            let symbol = offset_decoder.read_symbol(&mut bits)?;
+            let left = read_bucket(symbol, &mut bits)?.saturating_add(1);
+
+            writeln!(
+                writer,
+                "{:>8x}: M left: {:x}, count: {:x}",
+                cursor, left, count
+            )?;
+            cursor = cursor.saturating_add(count);
+        }
+
+        Ok(())
+    }
+}
+
+fn read_bucket(symbol: usize, reader: &mut BitReader) -> Result<usize> {
+    let extra = reader.read(bucket::extra_bits(symbol))?;
+    Ok(bucket::join(symbol, extra))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        huffman::MAX_CODE_BITS,
+        lz77::Parsing,
+        suffix_array::tests::{fibonacci, runs, Random},
+    };
+
+    fn check_roundtrip(data: &[u8]) {
+        for level in CompressOptions::MIN_LEVEL..=CompressOptions::MAX_LEVEL {
+            for window in [None, Some(1), Some(100)] {
+                let options = CompressOptions {
+                    window,
+                    ..CompressOptions::with_level(level)
+                };
+                let mut compressed = vec![];
+                LZ77Huffman::compress(data, 0, &options, &mut compressed).unwrap();
+
+                let mut extracted = vec![];
+                let size = LZ77Huffman::extract(
+                    &compressed,
+                    &[],
+                    data.len(),
+                    window.unwrap_or(usize::MAX),
+                    &mut extracted,
+                )
+                .unwrap();
+                assert_eq!(size, data.len());
+                assert_eq!(extracted, data, "{} {:?}", level, window);
+                LZ77Huffman::dump(&compressed, &mut std::io::sink()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn empty_input() {
+        // the end of the block is the only used symbol, and the offset table is empty
+        check_roundtrip(&[]);
+    }
+
+    #[test]
+    fn literal_only_blocks() {
+        let data: Vec<u8> = (0..=255).collect();
+        let options = CompressOptions {
+            parsing: Parsing::Greedy,
+            window: None,
+        };
+        assert!(LZ77::parse(&data, 0, &options)
+            .iter()
+            .all(|mode| matches!(mode, CompressMode::Pack { .. })));
+
+        check_roundtrip(&data);
+        check_roundtrip(&[42]);
+    }
+
+    #[test]
+    fn skewed_literals() {
+        // Fibonacci frequencies of 24 distinct bytes call for codes of up to 23 bits
+        let mut counts = vec![1usize, 1];
+        while counts.len() < 24 {
+            counts.push(counts[counts.len() - 1] + counts[counts.len() - 2]);
+        }
+        let sorted: Vec<u8> = counts
+            .iter()
+            .enumerate()
+            .rev()
+            .flat_map(|(byte, &count)| std::iter::repeat_n(byte as u8, count))
+            .collect();
+
+        // no byte repeats its neighbour, so a window of 1 leaves nothing but literals
+        let mut data = vec![0; sorted.len()];
+        let positions = (0..data.len()).step_by(2).chain((1..data.len()).step_by(2));
+        for (position, &byte) in positions.zip(sorted.iter()) {
+            data[position] = byte;
+        }
+        let options = CompressOptions {
+            parsing: Parsing::Greedy,
+            window: Some(1),
+        };
+        assert!(LZ77::parse(&data, 0, &options)
+            .iter()
+            .all(|mode| matches!(mode, CompressMode::Pack { .. })));
+
+        let mut frequencies = vec![0usize; LITERAL_COUNT];
+        frequencies[..counts.len()].copy_from_slice(&counts);
+        frequencies[END_SYMBOL] = 1;
+        let table = HuffmanTable::from_frequencies(&frequencies);
+        assert!(table
+            .lengths()
+            .iter()
+            .all(|&length| length as usize <= MAX_CODE_BITS));
+
+        let mut compressed = vec![];
+        LZ77Huffman::compress(&data, 0, &options, &mut compressed).unwrap();
+        let mut extracted = vec![];
+        LZ77Huffman::extract(&compressed, &[], data.len(), 1, &mut extracted).unwrap();
+        assert_eq!(extracted, data);
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(2);
+        for _ in 0..20 {
+            check_roundtrip(&random.bytes(1000));
+        }
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        // a single offset is the only symbol of its table
+        for data in runs() {
+            check_roundtrip(&data);
+        }
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(2000) {
+            check_roundtrip(&data);
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_blocks() {
+        let data = fibonacci(2000).pop().unwrap();
+        let mut compressed = vec![];
+        LZ77Huffman::compress(&data, 0, &CompressOptions::default(), &mut compressed).unwrap();
+
+        for size in 0..compressed.len() {
+            let mut extracted = vec![];
+            assert!(LZ77Huffman::extract(
+                &compressed[..size],
+                &[],
+                data.len(),
+                usize::MAX,
+                &mut extracted
+            )
+            .is_err());
+        }
+    }
+}
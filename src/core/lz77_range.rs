@@ -0,0 +1,313 @@
+use std::io::Write;
+
+use crate::error::{Error, Result};
+
+use super::{
+    bucket::{self, BUCKET_COUNT},
+    head::{Common, Head},
+    lz77::{CompressMode, CompressOptions, LZ77},
+    match_layout::Match,
+    range_coder::{RangeDecoder, RangeEncoder, PROBABILITY_INIT},
+};
+
+// Literals are modelled by the high bits of the byte before them.
+const LITERAL_CONTEXT_BITS: u32 = 3;
+// Offsets are modelled by the length of their match, up to this many contexts.
+const LENGTH_CONTEXTS: usize = 4;
+// The lowest extra bits of each bucket are modelled, the ones above are stored as is.
+const ALIGN_BITS: u32 = 4;
+const SLOT_BITS: u32 = BUCKET_COUNT.trailing_zeros();
+// Kinds of the previous two tokens.
+const HISTORY_STATES: usize = 4;
+
+// The LZ77 token stream range coded with adaptive models, much like LZMA.
+// Every block starts with its original size as a `Head<Common>`, and its models start afresh.
+pub struct LZ77Range;
+
+struct Model {
+    is_match: [u16; HISTORY_STATES],
+    is_repeat: [u16; HISTORY_STATES],
+    literals: Vec<u16>,
+    lengths: ValueModel,
+    offsets: Vec<ValueModel>,
+}
+
+// A bucket symbol coded as a bit tree, followed by its extra bits.
+struct ValueModel {
+    slots: Vec<u16>,
+    aligns: Vec<u16>,
+}
+
+impl Model {
+    fn new() -> Self {
+        Self {
+            is_match: [PROBABILITY_INIT; HISTORY_STATES],
+            is_repeat: [PROBABILITY_INIT; HISTORY_STATES],
+            literals: vec![PROBABILITY_INIT; 0x100 << LITERAL_CONTEXT_BITS],
+            lengths: ValueModel::new(),
+            offsets: (0..LENGTH_CONTEXTS).map(|_| ValueModel::new()).collect(),
+        }
+    }
+
+    fn literals(&mut self, previous: u8) -> &mut [u16] {
+        let context = (previous >> (u8::BITS - LITERAL_CONTEXT_BITS)) as usize;
+        &mut self.literals[(context << 8)..((context + 1) << 8)]
+    }
+
+    fn offsets(&mut self, count: usize) -> &mut ValueModel {
+        &mut self.offsets[(count - 1).min(LENGTH_CONTEXTS - 1)]
+    }
+}
+
+impl ValueModel {
+    fn new() -> Self {
+        Self {
+            slots: vec![PROBABILITY_INIT; BUCKET_COUNT],
+            aligns: vec![PROBABILITY_INIT; BUCKET_COUNT << ALIGN_BITS],
+        }
+    }
+
+    fn encode(&mut self, encoder: &mut RangeEncoder, value: usize) {
+        let (symbol, extra_bits, extra) = bucket::split(value);
+        encoder.encode_tree(&mut self.slots, SLOT_BITS, symbol);
+
+        let align_bits = extra_bits.min(ALIGN_BITS);
+        encoder.encode_direct(extra >> align_bits, extra_bits - align_bits);
+        encoder.encode_tree(
+            self.aligns(symbol),
+            align_bits,
+            (extra & ((1 << align_bits) - 1)) as usize,
+        );
+    }
+
+    fn decode(&mut self, decoder: &mut RangeDecoder) -> Result<usize> {
+        let symbol = decoder.decode_tree(&mut self.slots, SLOT_BITS)?;
+        let extra_bits = bucket::extra_bits(symbol);
+
+        let align_bits = extra_bits.min(ALIGN_BITS);
+        let high = decoder.decode_direct(extra_bits - align_bits)?;
+        let low = decoder.decode_tree(self.aligns(symbol), align_bits)? as u64;
+
+        Ok(bucket::join(symbol, (high << align_bits) | low))
+    }
+
+    fn aligns(&mut self, symbol: usize) -> &mut [u16] {
+        &mut self.aligns[(symbol << ALIGN_BITS)..((symbol + 1) << ALIGN_BITS)]
+    }
+}
+
+impl LZ77Range {
+    // Encodes `data[start..]`, with matches reaching back into `data[..start]` as in `LZ77`.
+    // The first literal is modelled after the byte before `start`.
+    pub fn compress(
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let mut model = Model::new();
+        let mut encoder = RangeEncoder::new();
+        let mut state = 0;
+        let mut last_left = 0;
+        let mut cursor = start;
+
+        for mode in LZ77::parse(data, start, options) {
+            match mode {
+                CompressMode::Pack { to, .. } => {
+                    for index in cursor..to {
+                        let previous = index.checked_sub(1).map_or(0, |index| data[index]);
+
+                        encoder.encode_bit(&mut model.is_match[state], 0);
+                        encoder.encode_tree(
+                            model.literals(previous),
+                            u8::BITS,
+                            data[index] as usize,
+                        );
+                        state = (state << 1) % HISTORY_STATES;
+                    }
+
+                    cursor = to;
+                }
+                CompressMode::Match { to, match_, .. } => {
+                    let repeat = match_.left == last_left;
+
+                    encoder.encode_bit(&mut model.is_match[state], 1);
+                    encoder.encode_bit(&mut model.is_repeat[state], repeat as u32);
+                    model.lengths.encode(&mut encoder, match_.count - 1);
+
+                    if !repeat {
+                        model
+                            .offsets(match_.count)
+                            .encode(&mut encoder, match_.left - 1);
+                    }
+
+                    last_left = match_.left;
+                    state = ((state << 1) | 1) % HISTORY_STATES;
+                    cursor = to;
+                }
+            }
+        }
+
+        Head::<Common>::write(&(data.len() - start), writer)?;
+        writer.write_all(&encoder.finish())
+    }
+
+    pub fn extract(
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        let mut size = 0;
+        let cursor = Head::<Common>::extract(buffer, &mut size)?;
+
+        if size > max_size {
+            return Err(Error::SizeMismatch {
+                expected: max_size,
+                actual: size,
+            });
+        }
+
+        let memory = Self::decode(&buffer[cursor..], history, size, window, |_| Ok(()))?;
+
+        writer.write_all(&memory[history.len()..])?;
+        Ok(size)
+    }
+
+    // Literal models depend on the bytes before them, so the tokens are extracted as they are
+    // described, after `history` and up to `max_size` bytes as in `extract`.
+    pub fn dump(
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        writer: &mut impl Write,
+    ) -> Result<()> {
+        let mut size = 0;
+        let cursor = Head::<Common>::extract(buffer, &mut size)?;
+        writeln!(writer, "{:>8x}: size: {:x}", 0, size)?;
+
+        if size > max_size {
+            return Err(Error::SizeMismatch {
+                expected: max_size,
+                actual: size,
+            });
+        }
+
+        let mut position = 0usize;
+        let mut literal_count = 0;
+
+        Self::decode(&buffer[cursor..], history, size, usize::MAX, |token| {
+            let Token::Match(match_) = token else {
+                literal_count += 1;
+                return Ok(());
+            };
+
+            if literal_count > 0 {
+                writeln!(writer, "{:>8x}: P count: {:x}", position, literal_count)?;
+                position += literal_count;
+                literal_count = 0;
+            }
+
+            writeln!(
+                writer,
+                "{:>8x}: M left: {:x}, count: {:x}",
+                position, match_.left, match_.count
+            )?;
+            position += match_.count;
+
+            Ok(())
+        })?;
+
+        if literal_count > 0 {
+            writeln!(writer, "{:>8x}: P count: {:x}", position, literal_count)?;
+        }
+
+        Ok(())
+    }
+
+    // Extracts `size` bytes after `history`, passing every token to `inspect` before it is
+    // applied. The returned memory starts with `history`.
+    fn decode(
+        buffer: &[u8],
+        history: &[u8],
+        size: usize,
+        window: usize,
+        mut inspect: impl FnMut(&Token) -> Result<()>,
+    ) -> Result<Vec<u8>> {
+        let mut model = Model::new();
+        let mut decoder = RangeDecoder::new(buffer)?;
+        let mut memory = history.to_vec();
+        let start = memory.len();
+        let mut state = 0;
+        let mut last_left = 0;
+
+        while memory.len() - start < size {
+            let previous = memory.last().copied().unwrap_or(0);
+
+            if decoder.decode_bit(&mut model.is_match[state])? == 0 {
+                let byte = decoder.decode_tree(model.literals(previous), u8::BITS)? as u8;
+                inspect(&Token::Literal)?;
+
+                memory.push(byte);
+                state = (state << 1) % HISTORY_STATES;
+                continue;
+            }
+
+            let repeat = decoder.decode_bit(&mut model.is_repeat[state])? != 0;
+            let count = model.lengths.decode(&mut decoder)?.saturating_add(1);
+            let left = if repeat {
+                last_left
+            } else {
+                model.offsets(count).decode(&mut decoder)?.saturating_add(1)
+            };
+
+            inspect(&Token::Match(Match { left, count }))?;
+
+            LZ77::copy_match(&mut memory, start, &Match { left, count }, size, window)?;
+            last_left = left;
+            state = ((state << 1) | 1) % HISTORY_STATES;
+        }
+
+        Ok(memory)
+    }
+}
+
+enum Token {
+    Literal,
+    Match(Match),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    const MAX_SIZE: usize = 1 << 16;
+
+    fn garbage(random: &mut Random, size: usize) -> Vec<u8> {
+        let mut block = vec![];
+        Head::<Common>::write(&size, &mut block).unwrap();
+        block.extend((0..40).map(|_| random.next() as u8));
+        block
+    }
+
+    #[test]
+    fn dump_rejects_sizes_beyond_the_block() {
+        let mut random = Random(1);
+        for _ in 0..100 {
+            let block = garbage(&mut random, 1 << 34);
+            assert!(LZ77Range::dump(&block, &[], MAX_SIZE, &mut std::io::sink()).is_err());
+        }
+    }
+
+    #[test]
+    fn dump_rejects_garbage_tokens() {
+        let mut random = Random(2);
+        for _ in 0..1000 {
+            let block = garbage(&mut random, MAX_SIZE);
+            assert!(LZ77Range::dump(&block, &[], MAX_SIZE, &mut std::io::sink()).is_err());
+            assert!(LZ77Range::extract(&block, &[], MAX_SIZE, usize::MAX, &mut vec![]).is_err());
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use std::io::{ErrorKind, Read};
+
+use crate::error::{Error, Result};
+
+use super::{
+    head::{Head, LeadingOne},
+    match_layout::MatchLayout,
+};
+
+// Compressed input is read in chunks of this size.
+const INPUT_SIZE: usize = 1 << 16;
+
+enum State {
+    Token,
+    Literals { remaining: usize },
+    Match { left: usize, remaining: usize },
+}
+
+// Extracts a headerless LZ77 token stream read incrementally from the underlying reader.
+// Only the last `window` bytes of output are kept to resolve matches, so memory stays bounded
+// however long the stream is. Records cut by a read are resumed once the rest is read.
+pub struct LZ77Decoder<R: Read> {
+    reader: R,
+    extractor: LZ77Extractor,
+}
+
+impl<R: Read> LZ77Decoder<R> {
+    pub fn new(reader: R, window: usize) -> Self {
+        Self {
+            reader,
+            extractor: LZ77Extractor::new(window),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read> Read for LZ77Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        Ok(self.extractor.read(&mut self.reader, buf)?)
+    }
+}
+
+// The state of `LZ77Decoder` apart from its reader, for streams embedded in other streams,
+// which pass a reader limited to the embedded stream to every `read`.
+pub struct LZ77Extractor {
+    input: Vec<u8>,
+    start: usize,
+    end: usize,
+    history: History,
+    state: State,
+}
+
+impl LZ77Extractor {
+    pub fn new(window: usize) -> Self {
+        Self {
+            input: vec![0u8; INPUT_SIZE],
+            start: 0,
+            end: 0,
+            history: History::new(window),
+            state: State::Token,
+        }
+    }
+
+    // Moves the unread input to the front and reads more after it.
+    // Returns false at the end of the underlying reader.
+    fn fill(&mut self, reader: &mut impl Read) -> Result<bool> {
+        self.input.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+
+        loop {
+            match reader.read(&mut self.input[self.end..]) {
+                Ok(read_size) => {
+                    self.end += read_size;
+                    return Ok(read_size > 0);
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            }
+        }
+    }
+
+    // Returns false when the stream ends between two records.
+    fn read_token(&mut self, reader: &mut impl Read) -> Result<bool> {
+        loop {
+            let buffer = &self.input[self.start..self.end];
+
+            if buffer.is_empty() {
+                if !self.fill(reader)? {
+                    return Ok(false);
+                }
+                continue;
+            }
+
+            let token = if MatchLayout::check(buffer) {
+                let mut match_ = MatchLayout::prepare(buffer);
+                MatchLayout::extract(buffer, &mut match_).map(|read_size| {
+                    let state = State::Match {
+                        left: match_.left,
+                        remaining: match_.count,
+                    };
+                    (read_size, state)
+                })
+            } else {
+                let mut count = 0;
+                Head::<LeadingOne>::extract(buffer, &mut count)
+                    .map(|read_size| (read_size, State::Literals { remaining: count }))
+            };
+
+            match token {
+                Ok((read_size, state)) => {
+                    if let State::Match { left, .. } = state {
+                        let window = self.history.size.min(self.history.window);
+                        if left == 0 || left > window {
+                            return Err(Error::OffsetBeyondWindow { left, window });
+                        }
+                    }
+
+                    self.start += read_size;
+                    self.state = state;
+                    return Ok(true);
+                }
+                // the record continues past what has been read so far
+                Err(Error::Truncated) => {
+                    if !self.fill(reader)? {
+                        return Err(Error::Truncated);
+                    }
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    // Extracts into `buf`, returning 0 once the stream read from `reader` ends.
+    pub fn read(&mut self, reader: &mut impl Read, buf: &mut [u8]) -> Result<usize> {
+        let mut written = 0;
+
+        while written < buf.len() {
+            let out = &mut buf[written..];
+
+            let copy_size = match self.state {
+                State::Token => {
+                    if !self.read_token(reader)? {
+                        break;
+                    }
+                    continue;
+                }
+                State::Literals { remaining } => {
+                    if self.start == self.end && !self.fill(reader)? {
+                        return Err(Error::Truncated);
+                    }
+
+                    let copy_size = remaining.min(out.len()).min(self.end - self.start);
+                    out[..copy_size]
+                        .copy_from_slice(&self.input[self.start..(self.start + copy_size)]);
+                    self.start += copy_size;
+
+                    self.state = State::Literals {
+                        remaining: remaining - copy_size,
+                    };
+                    copy_size
+                }
+                State::Match { left, remaining } => {
+                    // at most `left` bytes at once, so all of them are already in the history
+                    let copy_size = remaining.min(out.len()).min(left);
+                    self.history
+                        .copy(self.history.size - left, &mut out[..copy_size]);
+
+                    self.state = State::Match {
+                        left,
+                        remaining: remaining - copy_size,
+                    };
+                    copy_size
+                }
+            };
+
+            self.history.push(&out[..copy_size]);
+            written += copy_size;
+
+            if let State::Literals { remaining: 0 } | State::Match { remaining: 0, .. } = self.state
+            {
+                self.state = State::Token;
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+// Ring buffer of the last `window` extracted bytes, only growing as far as the output does.
+struct History {
+    buffer: Vec<u8>,
+    window: usize,
+    // bytes extracted so far, including the ones no longer kept
+    size: usize,
+}
+
+impl History {
+    fn new(window: usize) -> Self {
+        Self {
+            buffer: vec![],
+            window,
+            size: 0,
+        }
+    }
+
+    fn push(&mut self, mut data: &[u8]) {
+        if self.window == 0 {
+            self.size += data.len();
+            return;
+        }
+
+        while !data.is_empty() {
+            let copy_size = if self.buffer.len() < self.window {
+                let copy_size = data.len().min(self.window - self.buffer.len());
+                self.buffer.extend_from_slice(&data[..copy_size]);
+                copy_size
+            } else {
+                let index = self.size % self.window;
+                let copy_size = data.len().min(self.window - index);
+                self.buffer[index..(index + copy_size)].copy_from_slice(&data[..copy_size]);
+                copy_size
+            };
+
+            self.size += copy_size;
+            data = &data[copy_size..];
+        }
+    }
+
+    // Copies the bytes from `position` on, which must all be within the window.
+    fn copy(&self, position: usize, out: &mut [u8]) {
+        let index = position % self.window;
+        let first_size = out.len().min(self.buffer.len() - index);
+
+        let (first, second) = out.split_at_mut(first_size);
+        first.copy_from_slice(&self.buffer[index..(index + first_size)]);
+        second.copy_from_slice(&self.buffer[..second.len()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{
+        lz77::{CompressOptions, LZ77},
+        suffix_array::tests::{fibonacci, runs, Random},
+    };
+
+    // Hands out a single byte per read.
+    struct OneByte<'a>(&'a [u8]);
+
+    impl Read for OneByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some((&byte, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            if buf.is_empty() {
+                return Ok(0);
+            }
+
+            buf[0] = byte;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn read_by_byte(reader: &mut impl Read) -> std::io::Result<Vec<u8>> {
+        let mut extracted = vec![];
+        let mut buf = [0u8; 1];
+
+        while reader.read(&mut buf)? > 0 {
+            extracted.push(buf[0]);
+        }
+
+        Ok(extracted)
+    }
+
+    // Every record straddles reads, both of the compressed input and of the output.
+    fn check_extractor(data: &[u8]) {
+        for window in [1, 3, 100, usize::MAX] {
+            for level in [1, 6, 9] {
+                let options = CompressOptions {
+                    window: Some(window),
+                    ..CompressOptions::with_level(level)
+                };
+                let mut compressed = vec![];
+                LZ77::compress(data, 0, &options, &mut compressed).unwrap();
+
+                let mut decoder = LZ77Decoder::new(OneByte(&compressed), window);
+                assert_eq!(
+                    read_by_byte(&mut decoder).unwrap(),
+                    data,
+                    "{} {}",
+                    window,
+                    level
+                );
+
+                let mut decoder = LZ77Decoder::new(&compressed[..], window);
+                assert_eq!(
+                    read_by_byte(&mut decoder).unwrap(),
+                    data,
+                    "{} {}",
+                    window,
+                    level
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..30 {
+            check_extractor(&random.bytes(2000));
+        }
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        for data in runs() {
+            check_extractor(&data);
+        }
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(3000) {
+            check_extractor(&data);
+        }
+    }
+
+    #[test]
+    fn rejects_matches_beyond_the_window() {
+        let data = b"abcdefgh".repeat(10);
+        let mut compressed = vec![];
+        LZ77::compress(&data, 0, &CompressOptions::default(), &mut compressed).unwrap();
+
+        let mut decoder = LZ77Decoder::new(OneByte(&compressed), 7);
+        let error = read_by_byte(&mut decoder).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("match offset 8"), "{}", error);
+    }
+}
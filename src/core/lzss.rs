@@ -0,0 +1,315 @@
+use std::{io::Write, marker::PhantomData, str::FromStr};
+
+use crate::error::{Error, Result};
+
+use super::{
+    hash_chain::HashChain,
+    lz77::{CompressOptions, Parsing, LZ77},
+    match_layout::{
+        Match, MatchLayoutC2L13, MatchLayoutC3L12, MatchLayoutC4L11, MatchLayoutL7C8,
+        MatchLayoutTrait,
+    },
+};
+
+// Literal runs start with a byte whose top bit is clear, holding the run length minus one.
+const MAX_RUN: usize = 0x80;
+// A 2-byte match never pays off below this length.
+const MIN_COUNT: usize = 3;
+const DEFAULT_DEPTH: usize = 16;
+
+// Fixed 2-byte match token, trading offset bits for count bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LZSSLayout {
+    C2L13,
+    C3L12,
+    C4L11,
+    L7C8,
+}
+
+impl FromStr for LZSSLayout {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "c2l13" => Ok(Self::C2L13),
+            "c3l12" => Ok(Self::C3L12),
+            "c4l11" => Ok(Self::C4L11),
+            "l7c8" => Ok(Self::L7C8),
+            _ => Err(format!("unknown LZSS layout `{}`", value)),
+        }
+    }
+}
+
+// Byte-aligned tokens of one layout: a match when the top bit of the first byte is set,
+// otherwise a run of literal bytes following it.
+pub struct LZSS<L: MatchLayoutTrait> {
+    phantom: PhantomData<L>,
+}
+
+impl LZSSLayout {
+    pub fn compress(
+        &self,
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        match self {
+            Self::C2L13 => LZSS::<MatchLayoutC2L13>::compress(data, start, options, writer),
+            Self::C3L12 => LZSS::<MatchLayoutC3L12>::compress(data, start, options, writer),
+            Self::C4L11 => LZSS::<MatchLayoutC4L11>::compress(data, start, options, writer),
+            Self::L7C8 => LZSS::<MatchLayoutL7C8>::compress(data, start, options, writer),
+        }
+    }
+
+    pub fn extract(
+        &self,
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        match self {
+            Self::C2L13 => {
+                LZSS::<MatchLayoutC2L13>::extract(buffer, history, max_size, window, writer)
+            }
+            Self::C3L12 => {
+                LZSS::<MatchLayoutC3L12>::extract(buffer, history, max_size, window, writer)
+            }
+            Self::C4L11 => {
+                LZSS::<MatchLayoutC4L11>::extract(buffer, history, max_size, window, writer)
+            }
+            Self::L7C8 => {
+                LZSS::<MatchLayoutL7C8>::extract(buffer, history, max_size, window, writer)
+            }
+        }
+    }
+
+    pub fn dump(&self, buffer: &[u8], writer: &mut impl Write) -> Result<()> {
+        match self {
+            Self::C2L13 => LZSS::<MatchLayoutC2L13>::dump(buffer, writer),
+            Self::C3L12 => LZSS::<MatchLayoutC3L12>::dump(buffer, writer),
+            Self::C4L11 => LZSS::<MatchLayoutC4L11>::dump(buffer, writer),
+            Self::L7C8 => LZSS::<MatchLayoutL7C8>::dump(buffer, writer),
+        }
+    }
+}
+
+impl<L: MatchLayoutTrait> LZSS<L> {
+    // Greedy parse of `data[start..]` over a hash chain, examining as many candidates as a fast
+    // level would. Matches may reach back into `data[..start]`.
+    pub fn compress(
+        data: &[u8],
+        start: usize,
+        options: &CompressOptions,
+        writer: &mut impl Write,
+    ) -> std::io::Result<()> {
+        let depth = match options.parsing {
+            Parsing::Fast { depth } => depth,
+            _ => DEFAULT_DEPTH,
+        };
+        let window = options.window.unwrap_or(usize::MAX).min(L::MAX_LEFT);
+        let mut chain = HashChain::new(data, depth, window, L::MAX_COUNT);
+
+        let mut head = start;
+        let mut index = start;
+
+        while index < data.len() {
+            let Some(back) = chain.back(index).filter(|back| back.lcp >= MIN_COUNT) else {
+                index += 1;
+                continue;
+            };
+
+            Self::write_literals(&data[head..index], writer)?;
+            L::write(index - back.index, back.lcp, writer)?;
+
+            index += back.lcp;
+            head = index;
+        }
+
+        Self::write_literals(&data[head..], writer)
+    }
+
+    fn write_literals(data: &[u8], writer: &mut impl Write) -> std::io::Result<()> {
+        for run in data.chunks(MAX_RUN) {
+            writer.write_all(&[(run.len() - 1) as u8])?;
+            writer.write_all(run)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn extract(
+        buffer: &[u8],
+        history: &[u8],
+        max_size: usize,
+        window: usize,
+        writer: &mut impl Write,
+    ) -> Result<usize> {
+        let mut memory = history.to_vec();
+        let start = memory.len();
+
+        Self::decode(buffer, |token| {
+            match token {
+                Token::Match(match_) => {
+                    LZ77::copy_match(&mut memory, start, &match_, max_size, window)?
+                }
+                Token::Literals(literals) => {
+                    let extracted_size = memory.len() - start;
+                    if literals.len() > max_size - extracted_size {
+                        return Err(Error::SizeMismatch {
+                            expected: max_size,
+                            actual: extracted_size + literals.len(),
+                        });
+                    }
+
+                    memory.extend_from_slice(literals);
+                }
+            }
+
+            Ok(())
+        })?;
+
+        writer.write_all(&memory[start..])?;
+        Ok(memory.len() - start)
+    }
+
+    pub fn dump(buffer: &[u8], writer: &mut impl Write) -> Result<()> {
+        let mut head = 0;
+
+        Self::decode(buffer, |token| {
+            match token {
+                Token::Match(match_) => {
+                    writeln!(
+                        writer,
+                        "{:>8x} ({:>8x}): M left: {:x}, count: {:x}",
+                        head, 2, match_.left, match_.count
+                    )?;
+                    head += 2;
+                }
+                Token::Literals(literals) => {
+                    writeln!(
+                        writer,
+                        "{:>8x} ({:>8x}): P count: {:x}",
+                        head,
+                        1 + literals.len(),
+                        literals.len()
+                    )?;
+                    head += 1 + literals.len();
+                }
+            }
+
+            Ok(())
+        })
+    }
+
+    fn decode<'a>(buffer: &'a [u8], mut apply: impl FnMut(Token<'a>) -> Result<()>) -> Result<()> {
+        let mut head = 0;
+
+        while head < buffer.len() {
+            let token = if let Some(&[first, second]) = buffer.get(head..(head + 2)) {
+                [first, second]
+            } else {
+                [buffer[head], 0]
+            };
+
+            if L::check(&token) {
+                if head + 2 > buffer.len() {
+                    return Err(Error::Truncated);
+                }
+
+                apply(Token::Match(L::read(&token)))?;
+                head += 2;
+            } else {
+                let run = token[0] as usize + 1;
+                let Some(literals) = buffer.get((head + 1)..(head + 1 + run)) else {
+                    return Err(Error::Truncated);
+                };
+
+                apply(Token::Literals(literals))?;
+                head += 1 + run;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+enum Token<'a> {
+    Match(Match),
+    Literals(&'a [u8]),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::{fibonacci, runs, Random};
+
+    const LAYOUTS: [LZSSLayout; 4] = [
+        LZSSLayout::C2L13,
+        LZSSLayout::C3L12,
+        LZSSLayout::C4L11,
+        LZSSLayout::L7C8,
+    ];
+
+    fn check_roundtrip(data: &[u8]) {
+        for layout in LAYOUTS {
+            for (level, window) in [(1, None), (6, None), (6, Some(3)), (9, Some(100))] {
+                let options = CompressOptions {
+                    window,
+                    ..CompressOptions::with_level(level)
+                };
+                let mut compressed = vec![];
+                layout.compress(data, 0, &options, &mut compressed).unwrap();
+
+                let mut extracted = vec![];
+                let size = layout
+                    .extract(
+                        &compressed,
+                        &[],
+                        data.len(),
+                        window.unwrap_or(usize::MAX),
+                        &mut extracted,
+                    )
+                    .unwrap();
+                assert_eq!(size, data.len());
+                assert_eq!(extracted, data, "{:?} {} {:?}", layout, level, window);
+                layout.dump(&compressed, &mut std::io::sink()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..50 {
+            check_roundtrip(&random.bytes(1000));
+        }
+        check_roundtrip(&random.bytes(20000));
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        for data in runs() {
+            check_roundtrip(&data);
+        }
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(5000) {
+            check_roundtrip(&data);
+        }
+    }
+
+    #[test]
+    fn low_entropy_inputs() {
+        // long matches at every distance reach the count and offset limits of each layout
+        let mut random = Random(2);
+        let data: Vec<u8> = (0..50000)
+            .map(|_| b"ab"[(random.next() % 2) as usize])
+            .collect();
+        check_roundtrip(&data);
+    }
+}
@@ -1,5 +1,7 @@
 use std::io::Write;
 
+use crate::error::Result;
+
 use super::head::{Common, Head, HeadType, LeadingZero};
 
 #[derive(Debug)]
@@ -32,10 +34,10 @@ impl MatchLayout {
         }
     }
 
-    pub fn extract(buffer: &[u8], data: &mut Match) -> usize {
-        let mut cursor = Head::<LeadingZero>::extract(buffer, &mut data.count);
-        cursor += Head::<Common>::extract(&buffer[cursor..], &mut data.left);
-        cursor
+    pub fn extract(buffer: &[u8], data: &mut Match) -> Result<usize> {
+        let mut cursor = Head::<LeadingZero>::extract(buffer, &mut data.count)?;
+        cursor += Head::<Common>::extract(&buffer[cursor..], &mut data.left)?;
+        Ok(cursor)
     }
 }
 
@@ -57,7 +59,7 @@ impl MatchLayoutTrait for MatchLayoutC2L13 {
     const MAX_COUNT: usize = 4;
 
     fn check(buffer: &[u8; 2]) -> bool {
-        return (buffer[0] & 0x80u8) != 0;
+        (buffer[0] & 0x80u8) != 0
     }
 
     fn write(left: usize, count: usize, writer: &mut impl Write) -> std::io::Result<usize> {
@@ -89,7 +91,7 @@ impl MatchLayoutTrait for MatchLayoutC3L12 {
     const MAX_COUNT: usize = 8;
 
     fn check(buffer: &[u8; 2]) -> bool {
-        return (buffer[0] & 0x80u8) != 0;
+        (buffer[0] & 0x80u8) != 0
     }
 
     fn write(left: usize, count: usize, writer: &mut impl Write) -> std::io::Result<usize> {
@@ -121,7 +123,7 @@ impl MatchLayoutTrait for MatchLayoutC4L11 {
     const MAX_COUNT: usize = 16;
 
     fn check(buffer: &[u8; 2]) -> bool {
-        return (buffer[0] & 0x80u8) != 0;
+        (buffer[0] & 0x80u8) != 0
     }
 
     fn write(left: usize, count: usize, writer: &mut impl Write) -> std::io::Result<usize> {
@@ -153,7 +155,7 @@ impl MatchLayoutTrait for MatchLayoutL7C8 {
     const MAX_COUNT: usize = 256;
 
     fn check(buffer: &[u8; 2]) -> bool {
-        return (buffer[0] & 0x80u8) != 0;
+        (buffer[0] & 0x80u8) != 0
     }
 
     fn write(left: usize, count: usize, writer: &mut impl Write) -> std::io::Result<usize> {
@@ -163,7 +165,7 @@ impl MatchLayoutTrait for MatchLayoutL7C8 {
         // C: count
         let le_left = ((left - 1) as u64).to_le_bytes();
         let le_count = ((count - 1) as u64).to_le_bytes();
-        let buffer = [0x80u8 | le_count[0], le_left[0]];
+        let buffer = [0x80u8 | le_left[0], le_count[0]];
         writer.write_all(&buffer)?;
 
         Ok(buffer.len())
@@ -175,3 +177,70 @@ impl MatchLayoutTrait for MatchLayoutL7C8 {
         Match { left, count }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    // Every left and count the layout holds reads back as written, flagged as a match.
+    fn check_layout<L: MatchLayoutTrait>() {
+        for left in 1..=L::MAX_LEFT {
+            for count in 1..=L::MAX_COUNT {
+                let mut buffer = vec![];
+                assert_eq!(L::write(left, count, &mut buffer).unwrap(), 2);
+
+                let buffer = [buffer[0], buffer[1]];
+                assert!(L::check(&buffer));
+                let match_ = L::read(&buffer);
+                assert_eq!(
+                    (match_.left, match_.count),
+                    (left, count),
+                    "{:02x?}",
+                    buffer
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn c2l13_roundtrips() {
+        check_layout::<MatchLayoutC2L13>();
+    }
+
+    #[test]
+    fn c3l12_roundtrips() {
+        check_layout::<MatchLayoutC3L12>();
+    }
+
+    #[test]
+    fn c4l11_roundtrips() {
+        check_layout::<MatchLayoutC4L11>();
+    }
+
+    #[test]
+    fn l7c8_roundtrips() {
+        check_layout::<MatchLayoutL7C8>();
+    }
+
+    #[test]
+    fn variable_layout_roundtrips() {
+        let mut random = Random(1);
+        for _ in 0..10000 {
+            let data = Match {
+                left: 1 + (random.next() >> (random.next() % 64)) as usize % (1 << 40),
+                count: 1 + (random.next() >> (random.next() % 64)) as usize % (1 << 40),
+            };
+            let mut buffer = vec![0; MatchLayout::measure(&data)];
+            assert_eq!(MatchLayout::compress(&data, &mut buffer), buffer.len());
+            assert!(MatchLayout::check(&buffer));
+
+            let mut match_ = MatchLayout::prepare(&buffer);
+            assert_eq!(
+                MatchLayout::extract(&buffer, &mut match_).unwrap(),
+                buffer.len()
+            );
+            assert_eq!((match_.left, match_.count), (data.left, data.count));
+        }
+    }
+}
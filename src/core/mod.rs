@@ -1,11 +1,27 @@
 pub mod algebra;
 pub mod bar;
+pub mod bits;
+pub mod bucket;
 pub mod bwt;
+pub mod checksum;
+pub mod external_suffix_array;
+pub mod filter;
+pub mod frame;
+pub mod hash_chain;
 pub mod head;
+pub mod huffman;
 pub mod lz77;
+pub mod lz77_huffman;
+pub mod lz77_range;
+pub mod lz77_stream;
+pub mod lzss;
 pub mod match_layout;
 pub mod packed_bits;
+#[cfg(feature = "parallel")]
+pub mod parallel_suffix_array;
+pub mod range_coder;
 pub mod segment_tree;
 pub mod splitter;
+pub mod stream;
 pub mod suffix_array;
 pub mod suffix_reference;
@@ -1,3 +1,5 @@
+use crate::error::{Error, Result};
+
 use super::head::{Head, LeadingOne};
 
 pub struct PackedBits;
@@ -15,16 +17,41 @@ impl PackedBits {
         cursor + byte_count
     }
 
-    pub fn prepare(buffer: &[u8]) -> Vec<u8> {
+    pub fn prepare(buffer: &[u8]) -> Result<Vec<u8>> {
         let mut byte_count = 0;
-        Head::<LeadingOne>::extract(buffer, &mut byte_count);
-        vec![Default::default(); byte_count]
+        let cursor = Head::<LeadingOne>::extract(buffer, &mut byte_count)?;
+
+        if byte_count > buffer.len() - cursor {
+            return Err(Error::Truncated);
+        }
+
+        Ok(vec![Default::default(); byte_count])
+    }
+
+    pub fn extract(buffer: &[u8], data: &mut [u8]) -> Result<usize> {
+        let (packed, cursor) = Self::extract_slice(buffer)?;
+        let byte_count = packed.len();
+
+        if byte_count > data.len() {
+            return Err(Error::SizeMismatch {
+                expected: data.len(),
+                actual: byte_count,
+            });
+        }
+
+        data[..byte_count].copy_from_slice(packed);
+        Ok(cursor)
     }
 
-    pub fn extract(buffer: &[u8], data: &mut [u8]) -> usize {
+    // Packed bytes borrowed from `buffer`, along with the size read.
+    pub fn extract_slice(buffer: &[u8]) -> Result<(&[u8], usize)> {
         let mut byte_count = 0;
-        let cursor = Head::<LeadingOne>::extract(buffer, &mut byte_count);
-        data[..byte_count].copy_from_slice(&buffer[cursor..(cursor + byte_count)]);
-        cursor + byte_count
+        let cursor = Head::<LeadingOne>::extract(buffer, &mut byte_count)?;
+
+        let Some(packed) = buffer.get(cursor..(cursor.saturating_add(byte_count))) else {
+            return Err(Error::Truncated);
+        };
+
+        Ok((packed, cursor + byte_count))
     }
 }
@@ -0,0 +1,200 @@
+use rayon::prelude::*;
+
+use super::suffix_array::{BucketOption, SuffixIndex};
+
+// `suffix_array` hands inputs at least this long to `parallel_suffix_array` when the thread
+// pool has at least `PARALLEL_THREADS` threads. Prefix doubling does several times the work
+// of SA-IS, so it only pays off for large inputs spread over enough cores.
+pub const PARALLEL_THRESHOLD: usize = 1 << 22;
+pub const PARALLEL_THREADS: usize = 8;
+
+// Groups below this size are sorted on the current thread.
+const SEQUENTIAL_GROUP_SIZE: usize = 1 << 14;
+
+// Sorts suffixes by prefix doubling, as in Larsson-Sadakane: each round sorts every group of
+// suffixes sharing their first `k` items by the rank of the suffix `k` items later, in
+// parallel across and within groups, until all groups are single suffixes.
+// The result is the same as `suffix_array`.
+pub fn parallel_suffix_array<T, B: BucketOption<T>, I: SuffixIndex>(
+    data: &[T],
+    bucket_option: &B,
+) -> Vec<I> {
+    if data.is_empty() {
+        return vec![];
+    }
+
+    assert!(
+        data.len() < I::MAX,
+        "{} items do not fit the suffix index",
+        data.len()
+    );
+
+    let mut suffix_array: Vec<I> = (0..data.len()).map(I::from_usize).collect();
+    // a rank is one past the start of its group in the suffix array, 0 being past the end
+    let mut ranks: Vec<I> = vec![I::default(); data.len()];
+    let mut group_ranks: Vec<I> = vec![I::default(); data.len()];
+
+    let classes: Vec<I> = data
+        .iter()
+        .map(|value| I::from_usize(bucket_option.bucket_index(value)))
+        .collect();
+
+    let mut groups = vec![(0, data.len())];
+    let next_groups = refine(&mut suffix_array, &mut group_ranks, &groups, |index| {
+        classes[index.to_usize()]
+    });
+    update_ranks(&suffix_array, &group_ranks, &groups, &mut ranks);
+    groups = next_groups;
+    drop(classes);
+
+    let mut k = 1usize;
+
+    while !groups.is_empty() {
+        let next_groups = refine(&mut suffix_array, &mut group_ranks, &groups, |index| {
+            ranks.get(index.to_usize() + k).copied().unwrap_or_default()
+        });
+        // keys may read any rank, so ranks only change once every group is sorted
+        update_ranks(&suffix_array, &group_ranks, &groups, &mut ranks);
+        groups = next_groups;
+
+        k *= 2;
+    }
+
+    suffix_array
+}
+
+// Sorts each group by `key`, writing the rank of each suffix by its subgroup of equal keys
+// into `group_ranks`. Returns the subgroups still holding more than one suffix.
+fn refine<I: SuffixIndex, K: Ord + Send>(
+    suffix_array: &mut [I],
+    group_ranks: &mut [I],
+    groups: &[(usize, usize)],
+    key: impl Fn(I) -> K + Sync,
+) -> Vec<(usize, usize)> {
+    // split both arrays into the groups, all other suffixes being sorted already
+    let mut slices = Vec::with_capacity(groups.len());
+    let mut rest = (&mut suffix_array[..], &mut group_ranks[..]);
+    let mut offset = 0;
+
+    for &(start, stop) in groups {
+        let (_, indices) = std::mem::take(&mut rest.0).split_at_mut(start - offset);
+        let (indices, rest_indices) = indices.split_at_mut(stop - start);
+        let (_, new_ranks) = std::mem::take(&mut rest.1).split_at_mut(start - offset);
+        let (new_ranks, rest_ranks) = new_ranks.split_at_mut(stop - start);
+
+        slices.push((start, indices, new_ranks));
+        rest = (rest_indices, rest_ranks);
+        offset = stop;
+    }
+
+    slices
+        .into_par_iter()
+        .flat_map_iter(|(start, indices, new_ranks)| {
+            if indices.len() < SEQUENTIAL_GROUP_SIZE {
+                indices.sort_unstable_by_key(|&index| key(index));
+            } else {
+                indices.par_sort_unstable_by_key(|&index| key(index));
+            }
+
+            let mut next_groups = vec![];
+            let mut sub_start = 0;
+
+            for offset in 0..indices.len() {
+                if offset > 0 && key(indices[offset]) != key(indices[offset - 1]) {
+                    if offset - sub_start > 1 {
+                        next_groups.push((start + sub_start, start + offset));
+                    }
+                    sub_start = offset;
+                }
+
+                new_ranks[offset] = I::from_usize(start + sub_start + 1);
+            }
+
+            if indices.len() - sub_start > 1 {
+                next_groups.push((start + sub_start, start + indices.len()));
+            }
+
+            next_groups
+        })
+        .collect()
+}
+
+fn update_ranks<I: SuffixIndex>(
+    suffix_array: &[I],
+    group_ranks: &[I],
+    groups: &[(usize, usize)],
+    ranks: &mut [I],
+) {
+    for &(start, stop) in groups {
+        for rank in start..stop {
+            ranks[suffix_array[rank].to_usize()] = group_ranks[rank];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::{
+        suffix_array,
+        tests::{fibonacci, naive_suffix_array, runs, Random},
+        U8Bucket,
+    };
+
+    fn check<I: SuffixIndex>(data: &[u8]) {
+        let suffix_array: Vec<I> = parallel_suffix_array(data, &U8Bucket);
+        let suffix_array: Vec<usize> = suffix_array.into_iter().map(I::to_usize).collect();
+        assert_eq!(suffix_array, naive_suffix_array(data), "{:?}", data);
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..2000 {
+            check::<u32>(&random.bytes(300));
+        }
+        for _ in 0..10 {
+            check::<u64>(&random.bytes(20000));
+        }
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        for data in runs() {
+            check::<u32>(&data);
+        }
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(5000) {
+            check::<u32>(&data);
+        }
+    }
+
+    #[test]
+    fn groups_sorted_in_parallel() {
+        // a run long enough for groups above `SEQUENTIAL_GROUP_SIZE`
+        let data = vec![7u8; SEQUENTIAL_GROUP_SIZE * 4];
+        let suffix_array: Vec<u32> = parallel_suffix_array(&data, &U8Bucket);
+        assert!(suffix_array
+            .iter()
+            .rev()
+            .enumerate()
+            .all(|(index, &suffix)| suffix as usize == index));
+    }
+
+    #[test]
+    fn agrees_with_sa_is() {
+        // a binary string has groups that large in its first rounds
+        let mut random = Random(2);
+        let data: Vec<u8> = (0..(SEQUENTIAL_GROUP_SIZE * 8))
+            .map(|_| (random.next() % 2) as u8)
+            .collect();
+        let expected: Vec<u32> = suffix_array(&data, &U8Bucket);
+        assert_eq!(
+            parallel_suffix_array::<_, _, u32>(&data, &U8Bucket),
+            expected
+        );
+    }
+}
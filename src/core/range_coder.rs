@@ -0,0 +1,198 @@
+use crate::error::{Error, Result};
+
+// Binary range coder as in LZMA, with adaptive 11-bit probabilities of a 0 bit.
+const PROBABILITY_BITS: u32 = 11;
+const MOVE_BITS: u32 = 5;
+const TOP: u32 = 1 << 24;
+
+pub const PROBABILITY_INIT: u16 = 1 << (PROBABILITY_BITS - 1);
+
+pub struct RangeEncoder {
+    buffer: Vec<u8>,
+    low: u64,
+    range: u32,
+    cache: u8,
+    cache_size: usize,
+}
+
+impl RangeEncoder {
+    pub fn new() -> Self {
+        Self {
+            buffer: vec![],
+            low: 0,
+            range: u32::MAX,
+            cache: 0,
+            cache_size: 1,
+        }
+    }
+
+    pub fn encode_bit(&mut self, probability: &mut u16, bit: u32) {
+        let bound = (self.range >> PROBABILITY_BITS) * *probability as u32;
+
+        if bit == 0 {
+            self.range = bound;
+            *probability += ((1 << PROBABILITY_BITS) - *probability) >> MOVE_BITS;
+        } else {
+            self.low += bound as u64;
+            self.range -= bound;
+            *probability -= *probability >> MOVE_BITS;
+        }
+
+        self.normalize();
+    }
+
+    // Bits with a fixed probability of one half, from the most significant one.
+    pub fn encode_direct(&mut self, value: u64, bits: u32) {
+        for i in (0..bits).rev() {
+            self.range >>= 1;
+
+            if (value >> i) & 1 != 0 {
+                self.low += self.range as u64;
+            }
+
+            self.normalize();
+        }
+    }
+
+    // Bits from the most significant one, each modelled by the bits before it.
+    pub fn encode_tree(&mut self, probabilities: &mut [u16], bits: u32, value: usize) {
+        let mut node = 1;
+
+        for i in (0..bits).rev() {
+            let bit = ((value >> i) & 1) as u32;
+            self.encode_bit(&mut probabilities[node], bit);
+            node = (node << 1) | bit as usize;
+        }
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        for _ in 0..5 {
+            self.shift_low();
+        }
+
+        self.buffer
+    }
+
+    fn normalize(&mut self) {
+        while self.range < TOP {
+            self.range <<= 8;
+            self.shift_low();
+        }
+    }
+
+    // Holds back bytes of 0xFF until it is known whether a carry propagates into them.
+    fn shift_low(&mut self) {
+        if (self.low as u32) < 0xFF000000 || (self.low >> 32) != 0 {
+            let carry = (self.low >> 32) as u8;
+            let mut byte = self.cache;
+
+            loop {
+                self.buffer.push(byte.wrapping_add(carry));
+                byte = 0xFF;
+
+                self.cache_size -= 1;
+                if self.cache_size == 0 {
+                    break;
+                }
+            }
+
+            self.cache = (self.low >> 24) as u8;
+        }
+
+        self.cache_size += 1;
+        self.low = (self.low & 0x00FFFFFF) << 8;
+    }
+}
+
+impl Default for RangeEncoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct RangeDecoder<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+    range: u32,
+    code: u32,
+}
+
+impl<'a> RangeDecoder<'a> {
+    pub fn new(buffer: &'a [u8]) -> Result<Self> {
+        let mut decoder = Self {
+            buffer,
+            cursor: 0,
+            range: u32::MAX,
+            code: 0,
+        };
+
+        for _ in 0..5 {
+            decoder.code = (decoder.code << 8) | decoder.next_byte()? as u32;
+        }
+
+        Ok(decoder)
+    }
+
+    pub fn decode_bit(&mut self, probability: &mut u16) -> Result<u32> {
+        let bound = (self.range >> PROBABILITY_BITS) * *probability as u32;
+
+        let bit = if self.code < bound {
+            self.range = bound;
+            *probability += ((1 << PROBABILITY_BITS) - *probability) >> MOVE_BITS;
+            0
+        } else {
+            self.code -= bound;
+            self.range -= bound;
+            *probability -= *probability >> MOVE_BITS;
+            1
+        };
+
+        self.normalize()?;
+        Ok(bit)
+    }
+
+    pub fn decode_direct(&mut self, bits: u32) -> Result<u64> {
+        let mut value = 0u64;
+
+        for _ in 0..bits {
+            self.range >>= 1;
+
+            let bit = if self.code >= self.range {
+                self.code -= self.range;
+                1
+            } else {
+                0
+            };
+            value = (value << 1) | bit;
+
+            self.normalize()?;
+        }
+
+        Ok(value)
+    }
+
+    pub fn decode_tree(&mut self, probabilities: &mut [u16], bits: u32) -> Result<usize> {
+        let mut node = 1;
+
+        for _ in 0..bits {
+            node = (node << 1) | self.decode_bit(&mut probabilities[node])? as usize;
+        }
+
+        Ok(node - (1 << bits))
+    }
+
+    fn normalize(&mut self) -> Result<()> {
+        while self.range < TOP {
+            self.range <<= 8;
+            self.code = (self.code << 8) | self.next_byte()? as u32;
+        }
+
+        Ok(())
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let byte = *self.buffer.get(self.cursor).ok_or(Error::Truncated)?;
+        self.cursor += 1;
+        Ok(byte)
+    }
+}
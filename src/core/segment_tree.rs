@@ -21,10 +21,28 @@ where
         }
     }
 
+    pub fn from_values(values: impl ExactSizeIterator<Item = T>) -> Self {
+        let mut tree = Self::with_len(values.len());
+        let start_cursor = tree.start_cursor();
+
+        for (cursor, value) in (start_cursor..).zip(values) {
+            tree.data[cursor] = value;
+        }
+        for cursor in (1..start_cursor).rev() {
+            tree.data[cursor] = tree.data[cursor << 1].operate(&tree.data[(cursor << 1) | 1]);
+        }
+
+        tree
+    }
+
     pub fn len(&self) -> usize {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     fn cursor(&self, index: usize) -> usize {
         self.start_cursor() + index
     }
@@ -47,7 +65,87 @@ where
     }
 
     pub fn get<Idx: SegmentTreeIndex<T>>(&self, index: Idx) -> T {
-        index.get(&self)
+        index.get(self)
+    }
+
+    // Largest `end` for which `predicate` holds on `get(start..end)`, where `predicate` holds on
+    // the identity and, once failing, keeps failing as the range grows.
+    pub fn max_end(&self, start: usize, predicate: impl Fn(&T) -> bool) -> usize {
+        if start >= self.len {
+            return self.len;
+        }
+
+        let mut cursor = self.cursor(start);
+        let mut acc = T::identity();
+
+        loop {
+            while cursor & 1 == 0 {
+                cursor >>= 1;
+            }
+
+            let next_acc = acc.operate(&self.data[cursor]);
+            if !predicate(&next_acc) {
+                // descend to the first item failing it
+                while cursor < self.start_cursor() {
+                    cursor <<= 1;
+
+                    let next_acc = acc.operate(&self.data[cursor]);
+                    if predicate(&next_acc) {
+                        acc = next_acc;
+                        cursor += 1;
+                    }
+                }
+
+                return cursor - self.start_cursor();
+            }
+
+            acc = next_acc;
+            cursor += 1;
+
+            if cursor.is_power_of_two() {
+                return self.len;
+            }
+        }
+    }
+
+    // Smallest `start` for which `predicate` holds on `get(start..end)`, under the same
+    // conditions as `max_end`.
+    pub fn min_start(&self, end: usize, predicate: impl Fn(&T) -> bool) -> usize {
+        if end == 0 {
+            return 0;
+        }
+
+        let mut cursor = self.cursor(end);
+        let mut acc = T::identity();
+
+        loop {
+            cursor -= 1;
+            while cursor > 1 && cursor & 1 == 1 {
+                cursor >>= 1;
+            }
+
+            let next_acc = self.data[cursor].operate(&acc);
+            if !predicate(&next_acc) {
+                // descend to the last item failing it
+                while cursor < self.start_cursor() {
+                    cursor = (cursor << 1) | 1;
+
+                    let next_acc = self.data[cursor].operate(&acc);
+                    if predicate(&next_acc) {
+                        acc = next_acc;
+                        cursor -= 1;
+                    }
+                }
+
+                return cursor + 1 - self.start_cursor();
+            }
+
+            acc = next_acc;
+
+            if cursor.is_power_of_two() {
+                return 0;
+            }
+        }
     }
 }
 
@@ -119,3 +217,54 @@ where
         l_acc.operate(&r_acc)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::tests::Random;
+
+    #[derive(Clone, Copy, Debug)]
+    struct Addable(usize);
+
+    impl BinaryOperable for Addable {
+        fn operate(&self, other: &Self) -> Self {
+            Addable(self.0 + other.0)
+        }
+    }
+
+    impl Associative for Addable {}
+    impl WithIdentity for Addable {
+        fn identity() -> Self {
+            Addable(0)
+        }
+    }
+
+    #[test]
+    fn descents_agree_with_scans() {
+        let mut random = Random(1);
+
+        for len in 0..70 {
+            let values: Vec<usize> = (0..len).map(|_| random.next() as usize % 4).collect();
+            let tree = SegmentTree::from_values(values.iter().map(|&value| Addable(value)));
+            let sum = |range: Range<usize>| values[range].iter().sum::<usize>();
+
+            for limit in 0..12 {
+                let predicate = |acc: &Addable| acc.0 <= limit;
+
+                for start in 0..=len {
+                    let end = (start..=len).rev().find(|&end| sum(start..end) <= limit);
+                    assert_eq!(tree.max_end(start, predicate), end.unwrap(), "{:?}", values);
+                }
+                for end in 0..=len {
+                    let start = (0..=end).find(|&start| sum(start..end) <= limit);
+                    assert_eq!(
+                        tree.min_start(end, predicate),
+                        start.unwrap(),
+                        "{:?}",
+                        values
+                    );
+                }
+            }
+        }
+    }
+}
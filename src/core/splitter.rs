@@ -1,3 +1,5 @@
+use super::filter::Filter;
+
 pub type Full = Split<1>;
 pub type Half = Split<2>;
 pub type Quarter = Split<4>;
@@ -36,3 +38,78 @@ impl<const PARTITIONS: usize> Split<PARTITIONS> {
             .collect()
     }
 }
+
+// Sub-byte unit blocks are unrolled into before compression, as a filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Splitting {
+    Full,
+    Half,
+    Quarter,
+    Eighth,
+}
+
+impl Splitting {
+    pub fn bits(&self) -> usize {
+        u8::BITS as usize / self.partitions()
+    }
+
+    pub fn from_bits(bits: usize) -> Option<Self> {
+        match bits {
+            8 => Some(Self::Full),
+            4 => Some(Self::Half),
+            2 => Some(Self::Quarter),
+            1 => Some(Self::Eighth),
+            _ => None,
+        }
+    }
+
+    // Number of units every byte is unrolled into.
+    pub fn partitions(&self) -> usize {
+        match self {
+            Self::Full => 1,
+            Self::Half => 2,
+            Self::Quarter => 4,
+            Self::Eighth => 8,
+        }
+    }
+
+    pub fn unroll(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Full => Full::unroll(data),
+            Self::Half => Half::unroll(data),
+            Self::Quarter => Quarter::unroll(data),
+            Self::Eighth => Eighth::unroll(data),
+        }
+    }
+
+    pub fn roll(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Self::Full => Full::roll(data),
+            Self::Half => Half::roll(data),
+            Self::Quarter => Quarter::roll(data),
+            Self::Eighth => Eighth::roll(data),
+        }
+    }
+}
+
+impl Filter for Splitting {
+    fn id(&self) -> u8 {
+        3
+    }
+
+    fn parameter(&self) -> usize {
+        self.bits()
+    }
+
+    fn encoded_size(&self, size: usize) -> usize {
+        size.saturating_mul(self.partitions())
+    }
+
+    fn encode(&self, data: &[u8]) -> Vec<u8> {
+        self.unroll(data)
+    }
+
+    fn decode(&self, data: &[u8]) -> Vec<u8> {
+        self.roll(data)
+    }
+}
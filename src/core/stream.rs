@@ -0,0 +1,1003 @@
+use std::{
+    collections::VecDeque,
+    io::{ErrorKind, Read, Write},
+};
+
+use rayon::prelude::*;
+
+use crate::error::{Error, Result};
+
+use super::{
+    checksum::{Checksum, ChecksumHasher},
+    filter,
+    frame::{
+        BlockHeader, BlockHeaderLayout, Codec, FreezeOptions, Header, HeaderLayout,
+        MicrowaveOptions, VERSION,
+    },
+    lz77::CompressOptions,
+    lz77_stream::LZ77Extractor,
+};
+
+// Compresses everything written into it as a frozen stream.
+// Input is buffered up to `threads` blocks, which are compressed in parallel and written in order,
+// so the output does not depend on the thread count.
+// With a window, the window slides on from one block into the next, so matches reach back into
+// the blocks before as far as `Header::history_size`. Without one, blocks are independent.
+pub struct Encoder<W: Write> {
+    writer: Option<W>,
+    header: Header,
+    compress: CompressOptions,
+    threads: usize,
+    pool: Pool,
+    blocks: Vec<Vec<u8>>,
+    block: Vec<u8>,
+    // the end of the blocks written so far, as the filters left them
+    history: Vec<u8>,
+    stream_hasher: ChecksumHasher,
+    written_size: usize,
+    header_written: bool,
+    // set once a write or flush fails, after which dropping leaves the stream unfinished
+    failed: bool,
+}
+
+impl<W: Write> Encoder<W> {
+    pub fn new(writer: W, options: FreezeOptions) -> Self {
+        let header = Header {
+            version: VERSION,
+            codec: options.codec,
+            checksum: options.checksum,
+            filters: options.filters,
+            block_size: options.block_size,
+            window: options.compress.window,
+            linked: options.compress.window.is_some(),
+            original_size: options.original_size,
+        };
+
+        Self {
+            writer: Some(writer),
+            block: Vec::with_capacity(header.block_size),
+            blocks: vec![],
+            history: vec![],
+            stream_hasher: header.checksum.hasher(),
+            header,
+            compress: options.compress,
+            threads: options.threads.max(1),
+            pool: Pool::new(options.threads),
+            written_size: 0,
+            header_written: false,
+            failed: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        self.writer.as_ref().unwrap()
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        self.writer.as_mut().unwrap()
+    }
+
+    pub fn finish(mut self) -> Result<W> {
+        let result = self.try_finish();
+        let writer = self.writer.take().unwrap();
+        result.map(|_| writer)
+    }
+
+    fn try_finish(&mut self) -> Result<()> {
+        self.end_block();
+        self.write_blocks()?;
+
+        if let Some(original_size) = self.header.original_size {
+            if self.written_size != original_size {
+                return Err(Error::SizeMismatch {
+                    expected: original_size,
+                    actual: self.written_size,
+                });
+            }
+        }
+
+        let stream_hasher = std::mem::replace(&mut self.stream_hasher, Checksum::None.hasher());
+        let writer = self.writer.as_mut().unwrap();
+
+        BlockHeaderLayout::write(&BlockHeaderLayout::prepare(), writer)?;
+        writer.write_all(&stream_hasher.finalize())?;
+        writer.flush()?;
+
+        Ok(())
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+
+        if self.header.block_size == 0 {
+            return Err(Error::Io(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "block size must be positive",
+            )));
+        }
+
+        if self.header.filters.len() > u8::MAX as usize {
+            return Err(Error::Io(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "too many filters",
+            )));
+        }
+
+        HeaderLayout::write(&self.header, self.writer.as_mut().unwrap())?;
+        self.header_written = true;
+
+        Ok(())
+    }
+
+    // Queues the block being filled for compression.
+    fn end_block(&mut self) {
+        if !self.block.is_empty() {
+            let block =
+                std::mem::replace(&mut self.block, Vec::with_capacity(self.header.block_size));
+            self.blocks.push(block);
+        }
+    }
+
+    fn write_blocks(&mut self) -> Result<()> {
+        self.write_header()?;
+
+        let header = &self.header;
+        let compress = &self.compress;
+        let filtered = self.pool.map(&self.blocks, |block| {
+            filter::encode_all(&header.filters, block)
+        })?;
+
+        // linked blocks are compressed after the history they reach back into
+        let history_size = header.history_size();
+        let mut joined = std::mem::take(&mut self.history);
+        let inputs: Vec<(&[u8], usize)> = if history_size > 0 {
+            let mut spans = vec![];
+            for filtered in filtered.iter() {
+                let start = joined.len();
+                joined.extend_from_slice(filtered);
+                spans.push((start.saturating_sub(history_size), start, joined.len()));
+            }
+
+            spans
+                .into_iter()
+                .map(|(from, start, to)| (&joined[from..to], start - from))
+                .collect()
+        } else {
+            filtered.iter().map(|filtered| (&filtered[..], 0)).collect()
+        };
+
+        let frozen = self
+            .pool
+            .map(&inputs, |&(data, start)| -> std::io::Result<_> {
+                let mut compressed = vec![];
+                let codec = header
+                    .codec
+                    .compress(data, start, compress, &mut compressed)?;
+
+                Ok((codec, compressed))
+            })?;
+
+        let writer = self.writer.as_mut().unwrap();
+
+        for (block, frozen) in self.blocks.iter().zip(frozen) {
+            let (codec, compressed) = frozen?;
+
+            let block_header = BlockHeader {
+                original_size: block.len(),
+                compressed_size: compressed.len(),
+                codec: (header.codec == Codec::Auto).then_some(codec),
+            };
+
+            BlockHeaderLayout::write(&block_header, writer)?;
+            writer.write_all(&compressed)?;
+            writer.write_all(&header.checksum.digest(block))?;
+
+            self.stream_hasher.update(block);
+            self.written_size += block.len();
+        }
+
+        self.blocks.clear();
+        joined.drain(..joined.len().saturating_sub(history_size));
+        self.history = joined;
+
+        Ok(())
+    }
+
+    fn write_block_data(&mut self, buf: &[u8]) -> Result<usize> {
+        self.write_header()?;
+
+        let write_size = buf.len().min(self.header.block_size - self.block.len());
+        self.block.extend_from_slice(&buf[..write_size]);
+
+        if self.block.len() >= self.header.block_size {
+            self.end_block();
+
+            if self.blocks.len() >= self.threads {
+                self.write_blocks()?;
+            }
+        }
+
+        Ok(write_size)
+    }
+
+    fn flush_blocks(&mut self) -> std::io::Result<()> {
+        self.end_block();
+        self.write_blocks()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let result = self.write_block_data(buf);
+        self.failed |= result.is_err();
+        Ok(result?)
+    }
+
+    // Flushing ends the current block early, so the written data becomes extractable.
+    fn flush(&mut self) -> std::io::Result<()> {
+        let result = self.flush_blocks();
+        self.failed |= result.is_err();
+        result
+    }
+}
+
+impl<W: Write> Drop for Encoder<W> {
+    fn drop(&mut self) {
+        if self.writer.is_some() && !self.failed {
+            let _ = self.try_finish();
+        }
+    }
+}
+
+// A block as stored in the stream, before extraction.
+struct FrozenBlock {
+    original_size: usize,
+    codec: Codec,
+    compressed: Vec<u8>,
+    digest: Vec<u8>,
+}
+
+// An LZ77 block extracted as its payload is read, holding only the window in memory.
+struct StreamedBlock {
+    extractor: LZ77Extractor,
+    // payload not read yet
+    compressed_size: usize,
+    original_size: usize,
+    extracted_size: usize,
+    hasher: ChecksumHasher,
+}
+
+enum Frozen {
+    Block(FrozenBlock),
+    Streamed(StreamedBlock),
+    // the checksum of the whole stream
+    End(Vec<u8>),
+}
+
+// Extracts a frozen stream read from the underlying reader.
+// Up to `threads` blocks are read ahead and extracted in parallel, so only those are held in memory.
+pub struct Decoder<R: Read> {
+    reader: R,
+    options: MicrowaveOptions,
+    pool: Pool,
+    header: Option<Header>,
+    checksum: Checksum,
+    blocks: VecDeque<Vec<u8>>,
+    block: Vec<u8>,
+    cursor: usize,
+    // read out after `blocks`
+    streamed: Option<StreamedBlock>,
+    // what linked blocks reach back into: the end of the blocks extracted so far, as the filters
+    // left them, or the extractor of the last streamed block
+    history: Vec<u8>,
+    extractor: Option<LZ77Extractor>,
+    stream_hasher: ChecksumHasher,
+    extracted_size: usize,
+    // raised once the blocks before the failing one are read out
+    error: Option<Error>,
+    finished: bool,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, options: MicrowaveOptions) -> Self {
+        Self {
+            reader,
+            pool: Pool::new(options.threads),
+            options,
+            header: None,
+            checksum: Checksum::None,
+            blocks: VecDeque::new(),
+            block: vec![],
+            cursor: 0,
+            streamed: None,
+            history: vec![],
+            extractor: None,
+            stream_hasher: Checksum::None.hasher(),
+            extracted_size: 0,
+            error: None,
+            finished: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn header(&mut self) -> Result<&Header> {
+        if self.header.is_none() {
+            let mut header = HeaderLayout::prepare();
+            HeaderLayout::read(&mut self.reader, &mut header)?;
+
+            self.checksum = if self.options.verify {
+                header.checksum
+            } else {
+                Checksum::None
+            };
+            self.stream_hasher = self.checksum.hasher();
+            self.header = Some(header);
+        }
+
+        Ok(self.header.as_ref().unwrap())
+    }
+
+    fn read_frozen(&mut self) -> Result<Frozen> {
+        let (codec, checksum, block_size) = {
+            let header = self.header()?;
+            (header.codec, header.checksum, header.block_size)
+        };
+
+        let mut block_header = BlockHeaderLayout::prepare();
+        BlockHeaderLayout::read(&mut self.reader, &mut block_header, codec)?;
+
+        let mut digest = vec![0u8; checksum.size()];
+
+        if block_header.original_size == 0 {
+            self.reader.read_exact(&mut digest)?;
+            return Ok(Frozen::End(digest));
+        }
+
+        if block_header.original_size > block_size {
+            return Err(Error::BlockTooLarge {
+                size: block_header.original_size,
+                limit: block_size,
+            });
+        }
+
+        let codec = block_header.codec.unwrap_or(codec);
+        if let Some(window) = self.streaming_window(codec) {
+            let extractor = self.extractor.take();
+
+            return Ok(Frozen::Streamed(StreamedBlock {
+                extractor: extractor.unwrap_or_else(|| LZ77Extractor::new(window)),
+                compressed_size: block_header.compressed_size,
+                original_size: block_header.original_size,
+                extracted_size: 0,
+                hasher: self.checksum.hasher(),
+            }));
+        }
+
+        // grow the buffer only as far as the input actually reaches
+        let mut compressed = vec![];
+        (&mut self.reader)
+            .take(block_header.compressed_size as u64)
+            .read_to_end(&mut compressed)?;
+        if compressed.len() != block_header.compressed_size {
+            return Err(Error::Truncated);
+        }
+
+        self.reader.read_exact(&mut digest)?;
+
+        Ok(Frozen::Block(FrozenBlock {
+            original_size: block_header.original_size,
+            codec,
+            compressed,
+            digest,
+        }))
+    }
+
+    // The window a block is extracted with as it is read, which only LZ77 blocks without
+    // filters can be, when a window is known and blocks are not extracted in parallel.
+    // Linked blocks are all streamed or none is, so the history is never split between the two.
+    fn streaming_window(&self, codec: Codec) -> Option<usize> {
+        let header = self.header.as_ref().unwrap();
+        let window = header.window.into_iter().chain(self.options.window).min()?;
+        let codec = if header.linked { header.codec } else { codec };
+
+        (codec == Codec::LZ77 && header.filters.is_empty() && self.options.threads <= 1)
+            .then_some(window)
+    }
+
+    // Extracts the next bytes of the streamed block into `buf`, returning 0 once the whole
+    // block is extracted and verified.
+    fn read_streamed(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let block = self.streamed.as_mut().unwrap();
+        let mut payload = (&mut self.reader).take(block.compressed_size as u64);
+
+        let remaining = block.original_size - block.extracted_size;
+        let read_size = if remaining > 0 {
+            let max_size = buf.len().min(remaining);
+            let read_size = block.extractor.read(&mut payload, &mut buf[..max_size])?;
+            if read_size == 0 && payload.limit() > 0 {
+                return Err(Error::Truncated);
+            }
+            if read_size == 0 {
+                return Err(Error::SizeMismatch {
+                    expected: block.original_size,
+                    actual: block.extracted_size,
+                });
+            }
+            read_size
+        } else {
+            // the payload must end along with the block
+            let excess_size = block.extractor.read(&mut payload, &mut [0u8; 1])?;
+            if excess_size > 0 {
+                return Err(Error::SizeMismatch {
+                    expected: block.original_size,
+                    actual: block.original_size + excess_size,
+                });
+            }
+            0
+        };
+
+        block.compressed_size = payload.limit() as usize;
+
+        if read_size > 0 {
+            block.hasher.update(&buf[..read_size]);
+            self.stream_hasher.update(&buf[..read_size]);
+            block.extracted_size += read_size;
+            return Ok(read_size);
+        }
+
+        let block = self.streamed.take().unwrap();
+        let mut digest = vec![0u8; self.header.as_ref().unwrap().checksum.size()];
+        self.reader.read_exact(&mut digest)?;
+
+        if block.hasher.finalize() != digest[..self.checksum.size()] {
+            return Err(Error::ChecksumMismatch {
+                offset: Some(self.extracted_size),
+            });
+        }
+        self.extracted_size += block.original_size;
+        if self.header.as_ref().unwrap().linked {
+            self.extractor = Some(block.extractor);
+        }
+
+        Ok(0)
+    }
+
+    fn read_blocks(&mut self) -> Result<()> {
+        self.header()?;
+
+        let mut frozen = vec![];
+        let mut streamed = None;
+        let mut stream_digest = None;
+        let mut error = None;
+
+        while frozen.len() < self.options.threads.max(1) {
+            match self.read_frozen() {
+                Ok(Frozen::Block(block)) => frozen.push(block),
+                Ok(Frozen::Streamed(block)) => {
+                    streamed = Some(block);
+                    break;
+                }
+                Ok(Frozen::End(digest)) => {
+                    stream_digest = Some(digest);
+                    break;
+                }
+                Err(read_error) => {
+                    error = Some(read_error);
+                    break;
+                }
+            }
+        }
+
+        let header = self.header.as_ref().unwrap();
+        let window = header.window.into_iter().chain(self.options.window).min();
+        let window = window.unwrap_or(usize::MAX);
+        let extracted: Vec<_> = if header.history_size() > 0 {
+            // every linked block is extracted after the one before it
+            frozen
+                .iter()
+                .map(|block| extract_block(header, &mut self.history, window, block))
+                .collect()
+        } else {
+            self.pool.map(&frozen, |block| {
+                extract_block(header, &mut vec![], window, block)
+            })?
+        };
+
+        for (block, extracted) in frozen.iter().zip(extracted) {
+            let extracted = extracted?;
+
+            if self.checksum.digest(&extracted) != block.digest[..self.checksum.size()] {
+                return Err(Error::ChecksumMismatch {
+                    offset: Some(self.extracted_size),
+                });
+            }
+
+            self.stream_hasher.update(&extracted);
+            self.extracted_size += extracted.len();
+            self.blocks.push_back(extracted);
+        }
+
+        self.streamed = streamed;
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let Some(digest) = stream_digest else {
+            return Ok(());
+        };
+
+        self.finished = true;
+
+        let stream_hasher = std::mem::replace(&mut self.stream_hasher, self.checksum.hasher());
+        if stream_hasher.finalize() != digest[..self.checksum.size()] {
+            return Err(Error::ChecksumMismatch { offset: None });
+        }
+
+        if let Some(original_size) = header.original_size {
+            if self.extracted_size != original_size {
+                return Err(Error::SizeMismatch {
+                    expected: original_size,
+                    actual: self.extracted_size,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Extracts a block after `history`, which then moves on past the block when blocks are linked.
+fn extract_block(
+    header: &Header,
+    history: &mut Vec<u8>,
+    window: usize,
+    block: &FrozenBlock,
+) -> Result<Vec<u8>> {
+    let mut extracted = vec![];
+
+    let extracted_size = if header.filters.is_empty() {
+        let extracted_size = block.codec.extract(
+            &block.compressed,
+            history,
+            block.original_size,
+            window,
+            &mut extracted,
+        )?;
+        slide_history(header, history, &extracted);
+        extracted_size
+    } else {
+        let filtered_size = filter::encoded_size(&header.filters, block.original_size);
+        let mut filtered = vec![];
+        block.codec.extract(
+            &block.compressed,
+            history,
+            filtered_size,
+            window,
+            &mut filtered,
+        )?;
+
+        if filtered.len() != filtered_size {
+            return Err(Error::SizeMismatch {
+                expected: filtered_size,
+                actual: filtered.len(),
+            });
+        }
+        slide_history(header, history, &filtered);
+
+        extracted = filter::decode_all(&header.filters, &filtered).into_owned();
+        extracted.len()
+    };
+
+    if extracted_size != block.original_size {
+        return Err(Error::SizeMismatch {
+            expected: block.original_size,
+            actual: extracted_size,
+        });
+    }
+
+    Ok(extracted)
+}
+
+// Keeps the last `Header::history_size` bytes of `history` followed by `data`.
+fn slide_history(header: &Header, history: &mut Vec<u8>, data: &[u8]) {
+    let history_size = header.history_size();
+    if history_size == 0 {
+        return;
+    }
+
+    history.extend_from_slice(data);
+    history.drain(..history.len().saturating_sub(history_size));
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        while self.cursor >= self.block.len() {
+            if let Some(block) = self.blocks.pop_front() {
+                self.block = block;
+                self.cursor = 0;
+                continue;
+            }
+
+            if self.streamed.is_some() {
+                match self.read_streamed(buf) {
+                    Ok(0) => continue,
+                    Ok(read_size) => return Ok(read_size),
+                    Err(error) => {
+                        self.streamed = None;
+                        self.finished = true;
+                        return Err(error.into());
+                    }
+                }
+            }
+
+            if let Some(error) = self.error.take() {
+                return Err(error.into());
+            }
+
+            if self.finished {
+                return Ok(0);
+            }
+
+            // blocks extracted before a failure are still read out first
+            if let Err(error) = self.read_blocks() {
+                self.finished = true;
+                self.error = Some(error);
+            }
+        }
+
+        let read_size = buf.len().min(self.block.len() - self.cursor);
+        buf[..read_size].copy_from_slice(&self.block[self.cursor..(self.cursor + read_size)]);
+        self.cursor += read_size;
+
+        Ok(read_size)
+    }
+}
+
+// Threads blocks are compressed or extracted on, started on first use and kept for the whole
+// stream.
+struct Pool {
+    threads: usize,
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl Pool {
+    fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            pool: None,
+        }
+    }
+
+    // Maps every item, in parallel when there are several, keeping their order.
+    fn map<'a, T: Sync, U: Send>(
+        &mut self,
+        items: &'a [T],
+        map: impl Fn(&'a T) -> U + Sync,
+    ) -> std::io::Result<Vec<U>> {
+        if items.len() <= 1 || self.threads <= 1 {
+            return Ok(items.iter().map(map).collect());
+        }
+
+        let pool = match &mut self.pool {
+            Some(pool) => pool,
+            pool => pool.insert(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(self.threads)
+                    .build()
+                    .map_err(std::io::Error::other)?,
+            ),
+        };
+
+        Ok(pool.install(|| items.par_iter().map(&map).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::core::{filter::FilterStage, lzss::LZSSLayout, suffix_array::tests::Random};
+
+    const BLOCK_SIZE: usize = 4096;
+
+    // Words from a small vocabulary, so matches are found within and across blocks.
+    fn sample(random: &mut Random, size: usize) -> Vec<u8> {
+        let words: Vec<Vec<u8>> = (0..64)
+            .map(|_| {
+                let length = 2 + random.next() as usize % 10;
+                (0..length)
+                    .map(|_| b'a' + (random.next() % 26) as u8)
+                    .collect()
+            })
+            .collect();
+
+        let mut data = vec![];
+        while data.len() < size {
+            data.extend_from_slice(&words[random.next() as usize % words.len()]);
+            data.push(b' ');
+        }
+        data.truncate(size);
+        data
+    }
+
+    fn freeze_options(codec: Codec, window: Option<usize>) -> FreezeOptions {
+        FreezeOptions {
+            codec,
+            block_size: BLOCK_SIZE,
+            compress: CompressOptions {
+                window,
+                ..CompressOptions::with_level(4)
+            },
+            ..Default::default()
+        }
+    }
+
+    fn freeze(data: &[u8], options: &FreezeOptions) -> Vec<u8> {
+        let mut encoder = Encoder::new(vec![], options.clone());
+        std::io::copy(&mut Cursor::new(data), &mut encoder).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn microwave(frozen: &[u8], threads: usize) -> std::io::Result<Vec<u8>> {
+        let options = MicrowaveOptions {
+            threads,
+            ..Default::default()
+        };
+        let mut extracted = vec![];
+        std::io::copy(&mut Decoder::new(frozen, options), &mut extracted)?;
+        Ok(extracted)
+    }
+
+    // Hands out a few bytes at a time, as pipes and sockets do.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        sizes: std::iter::Cycle<std::array::IntoIter<usize, 5>>,
+    }
+
+    impl<'a> Trickle<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            Self {
+                data,
+                sizes: [1, 7, 2, 300, 4999].into_iter().cycle(),
+            }
+        }
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let size = buf
+                .len()
+                .min(self.data.len())
+                .min(self.sizes.next().unwrap());
+            buf[..size].copy_from_slice(&self.data[..size]);
+            self.data = &self.data[size..];
+            Ok(size)
+        }
+    }
+
+    #[test]
+    fn odd_write_and_read_sizes() {
+        let mut random = Random(1);
+        let data = sample(&mut random, 10 * BLOCK_SIZE + 123);
+
+        for window in [None, Some(BLOCK_SIZE)] {
+            let options = freeze_options(Codec::LZ77, window);
+            let frozen = freeze(&data, &options);
+
+            let mut encoder = Encoder::new(vec![], options.clone());
+            std::io::copy(&mut Trickle::new(&data), &mut encoder).unwrap();
+            assert_eq!(encoder.finish().unwrap(), frozen);
+
+            for threads in [1, 3] {
+                let options = MicrowaveOptions {
+                    threads,
+                    ..Default::default()
+                };
+                let mut decoder = Decoder::new(Trickle::new(&frozen), options);
+                let mut extracted = vec![];
+                let mut buf = [0u8; 997];
+                loop {
+                    let size = random.next() as usize % buf.len() + 1;
+                    match decoder.read(&mut buf[..size]).unwrap() {
+                        0 => break,
+                        read_size => extracted.extend_from_slice(&buf[..read_size]),
+                    }
+                }
+                assert_eq!(extracted, data, "{:?} {}", window, threads);
+            }
+        }
+    }
+
+    #[test]
+    fn flush_mid_stream() {
+        let mut random = Random(2);
+        let data = sample(&mut random, 3 * BLOCK_SIZE);
+        let (head, tail) = data.split_at(BLOCK_SIZE + 1000);
+
+        for window in [None, Some(BLOCK_SIZE)] {
+            let mut encoder = Encoder::new(vec![], freeze_options(Codec::LZ77, window));
+            encoder.write_all(head).unwrap();
+            encoder.flush().unwrap();
+
+            // everything written before the flush extracts, before the stream is cut short
+            let flushed = encoder.get_ref().clone();
+            let mut decoder = Decoder::new(&flushed[..], MicrowaveOptions::default());
+            let mut extracted = vec![];
+            assert!(decoder.read_to_end(&mut extracted).is_err());
+            assert_eq!(extracted, head);
+
+            encoder.write_all(tail).unwrap();
+            let frozen = encoder.finish().unwrap();
+            assert!(frozen.starts_with(&flushed));
+            assert_eq!(microwave(&frozen, 1).unwrap(), data);
+            assert_eq!(microwave(&frozen, 3).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn drop_finishes_stream() {
+        let mut random = Random(3);
+        let data = sample(&mut random, 2 * BLOCK_SIZE + 5);
+        let options = freeze_options(Codec::LZ77, Some(BLOCK_SIZE));
+
+        let mut frozen = vec![];
+        {
+            let mut encoder = Encoder::new(&mut frozen, options.clone());
+            encoder.write_all(&data).unwrap();
+        }
+
+        assert_eq!(frozen, freeze(&data, &options));
+        assert_eq!(microwave(&frozen, 1).unwrap(), data);
+    }
+
+    #[test]
+    fn window_option_bounds_every_block() {
+        let mut random = Random(6);
+        let text = sample(&mut random, 1000);
+        let data = text.repeat(3);
+
+        for codec in [Codec::LZ77, Codec::LZ77Huffman] {
+            let frozen = freeze(&data, &freeze_options(codec, None));
+
+            for threads in [1, 3] {
+                let options = MicrowaveOptions {
+                    threads,
+                    window: Some(100),
+                    ..Default::default()
+                };
+                let mut extracted = vec![];
+                let result = std::io::copy(&mut Decoder::new(&frozen[..], options), &mut extracted);
+                assert!(result.is_err(), "{:?} {}", codec, threads);
+
+                let options = MicrowaveOptions {
+                    threads,
+                    window: Some(1000),
+                    ..Default::default()
+                };
+                let mut extracted = vec![];
+                std::io::copy(&mut Decoder::new(&frozen[..], options), &mut extracted).unwrap();
+                assert_eq!(extracted, data);
+            }
+        }
+    }
+
+    #[test]
+    fn linked_blocks_reach_back() {
+        let mut random = Random(4);
+        let text = sample(&mut random, BLOCK_SIZE);
+        let data = text.repeat(8);
+
+        let unlinked = freeze(&data, &freeze_options(Codec::LZ77, None));
+        let linked = freeze(&data, &freeze_options(Codec::LZ77, Some(BLOCK_SIZE)));
+        assert!(
+            linked.len() * 4 < unlinked.len(),
+            "{} {}",
+            linked.len(),
+            unlinked.len()
+        );
+
+        let codecs = [
+            Codec::LZ77,
+            Codec::LZ77Huffman,
+            Codec::LZ77Range,
+            Codec::BWT,
+            Codec::LZSS(LZSSLayout::C3L12),
+            Codec::Stored,
+            Codec::Auto,
+        ];
+        let data = [text.repeat(2), sample(&mut random, 5 * BLOCK_SIZE)].concat();
+
+        for codec in codecs {
+            for filters in [vec![], vec!["delta:1".parse::<FilterStage>().unwrap()]] {
+                for window in [1, 100, BLOCK_SIZE, 3 * BLOCK_SIZE] {
+                    let options = FreezeOptions {
+                        filters: filters.clone(),
+                        ..freeze_options(codec, Some(window))
+                    };
+                    let frozen = freeze(&data, &options);
+
+                    for threads in [1, 3] {
+                        let extracted = microwave(&frozen, threads).unwrap();
+                        assert_eq!(
+                            extracted, data,
+                            "{:?} {:?} {} {}",
+                            codec, filters, window, threads
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn output_is_the_same_for_any_thread_count() {
+        let mut random = Random(6);
+        let data = sample(&mut random, 5 * BLOCK_SIZE + 123);
+
+        for codec in [Codec::LZ77, Codec::LZ77Range, Codec::BWT, Codec::Auto] {
+            for window in [None, Some(BLOCK_SIZE)] {
+                let options = freeze_options(codec, window);
+                let frozen = freeze(&data, &options);
+
+                for threads in [2, 8] {
+                    let options = FreezeOptions {
+                        threads,
+                        ..options.clone()
+                    };
+                    assert_eq!(
+                        freeze(&data, &options),
+                        frozen,
+                        "{:?} {:?} {}",
+                        codec,
+                        window,
+                        threads
+                    );
+                }
+
+                for threads in [1, 2, 8] {
+                    let extracted = microwave(&frozen, threads).unwrap();
+                    assert_eq!(extracted, data, "{:?} {:?} {}", codec, window, threads);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn linked_blocks_after_flushes() {
+        // every flush ends a short block, so the history spans many of them
+        let mut random = Random(5);
+        let data = sample(&mut random, 3 * BLOCK_SIZE);
+
+        for codec in [Codec::LZ77, Codec::LZ77Range] {
+            let mut encoder = Encoder::new(vec![], freeze_options(codec, Some(BLOCK_SIZE)));
+            for chunk in data.chunks(100) {
+                encoder.write_all(chunk).unwrap();
+                encoder.flush().unwrap();
+            }
+            let frozen = encoder.finish().unwrap();
+
+            assert_eq!(microwave(&frozen, 1).unwrap(), data);
+            assert_eq!(microwave(&frozen, 3).unwrap(), data);
+        }
+    }
+}
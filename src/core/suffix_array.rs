@@ -1,4 +1,61 @@
-use std::marker::PhantomData;
+use std::{fmt::Debug, io::Write, marker::PhantomData};
+
+#[cfg(feature = "parallel")]
+use super::parallel_suffix_array::{parallel_suffix_array, PARALLEL_THREADS, PARALLEL_THRESHOLD};
+
+mod sealed {
+    pub trait Sealed {}
+
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
+}
+
+// Integer type positions are stored as, so that inputs under 4 GiB need half the memory with `u32`.
+// Only implemented for `u32` and `u64`, so that arrays of indices on disk can be mapped as slices.
+pub trait SuffixIndex: sealed::Sealed + Copy + Default + Ord + Debug + Send + Sync {
+    const MAX: usize;
+    const SIZE: usize = std::mem::size_of::<Self>();
+
+    fn from_usize(value: usize) -> Self;
+
+    fn to_usize(self) -> usize;
+
+    // Writes the index in native byte order, as mapped back by `SuffixArrayFile`.
+    fn write(self, writer: &mut impl Write) -> std::io::Result<()>;
+}
+
+impl SuffixIndex for u32 {
+    const MAX: usize = u32::MAX as usize;
+
+    fn from_usize(value: usize) -> Self {
+        debug_assert!(value <= <Self as SuffixIndex>::MAX);
+        value as u32
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+
+    fn write(self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_ne_bytes())
+    }
+}
+
+impl SuffixIndex for u64 {
+    const MAX: usize = usize::MAX;
+
+    fn from_usize(value: usize) -> Self {
+        value as u64
+    }
+
+    fn to_usize(self) -> usize {
+        self as usize
+    }
+
+    fn write(self, writer: &mut impl Write) -> std::io::Result<()> {
+        writer.write_all(&self.to_ne_bytes())
+    }
+}
 
 pub trait BucketOption<T> {
     fn size(&self) -> usize;
@@ -9,11 +66,11 @@ pub struct U8Bucket;
 
 impl BucketOption<u8> for U8Bucket {
     fn size(&self) -> usize {
-        return 256;
+        256
     }
 
     fn bucket_index(&self, value: &u8) -> usize {
-        return (*value) as usize;
+        (*value) as usize
     }
 }
 
@@ -21,20 +78,20 @@ pub struct IndexBucket {
     size: usize,
 }
 
-impl BucketOption<usize> for IndexBucket {
+impl<I: SuffixIndex> BucketOption<I> for IndexBucket {
     fn size(&self) -> usize {
-        return self.size;
+        self.size
     }
 
-    fn bucket_index(&self, value: &usize) -> usize {
-        return *value;
+    fn bucket_index(&self, value: &I) -> usize {
+        value.to_usize()
     }
 }
 
 #[derive(Clone)]
-pub struct SuffixArrayBucket<'a, T, Bucket: BucketOption<T>> {
+pub struct SuffixArrayBucket<'a, T, Bucket: BucketOption<T>, I: SuffixIndex> {
     bucket_option: &'a Bucket,
-    indices: Vec<usize>,
+    indices: Vec<I>,
     bins: Vec<BucketBin>,
     phantom: PhantomData<T>,
 }
@@ -47,17 +104,32 @@ struct BucketBin {
     s_count: usize,
 }
 
-impl<'a, T, Bucket: BucketOption<T>> SuffixArrayBucket<'a, T, Bucket> {
+impl<'a, T, Bucket: BucketOption<T>, I: SuffixIndex> SuffixArrayBucket<'a, T, Bucket, I> {
     pub fn new(data: &[T], types: &[SuffixType], bucket_option: &'a Bucket) -> Self {
-        let mut bins = vec![
+        Self::with_buffers(data, types, bucket_option, vec![], vec![])
+    }
+
+    // Builds the bucket over buffers left by `into_buffers`, reusing their allocations.
+    fn with_buffers(
+        data: &[T],
+        types: &[SuffixType],
+        bucket_option: &'a Bucket,
+        mut indices: Vec<I>,
+        mut bins: Vec<BucketBin>,
+    ) -> Self {
+        indices.clear();
+        indices.resize(data.len(), I::default());
+
+        bins.clear();
+        bins.resize(
+            bucket_option.size(),
             BucketBin {
                 l_start: 0,
                 l_count: 0,
                 s_stop: 0,
-                s_count: 0
-            };
-            bucket_option.size()
-        ];
+                s_count: 0,
+            },
+        );
 
         for (value, suffix_type) in data.iter().zip(types.iter()) {
             let bucket_index = bucket_option.bucket_index(value);
@@ -89,16 +161,24 @@ impl<'a, T, Bucket: BucketOption<T>> SuffixArrayBucket<'a, T, Bucket> {
 
         Self {
             bucket_option,
-            indices: vec![0; data.len()],
+            indices,
             bins,
             phantom: PhantomData,
         }
     }
 
+    fn into_buffers(self) -> (Vec<I>, Vec<BucketBin>) {
+        (self.indices, self.bins)
+    }
+
     pub fn len(&self) -> usize {
         self.indices.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
     pub fn num_bins(&self) -> usize {
         self.bins.len()
     }
@@ -113,42 +193,42 @@ impl<'a, T, Bucket: BucketOption<T>> SuffixArrayBucket<'a, T, Bucket> {
 
     pub fn l_index_by_rank(&self, bin: usize, rank: usize) -> usize {
         let bin = &self.bins[bin];
-        self.indices[bin.l_start + rank]
+        self.indices[bin.l_start + rank].to_usize()
     }
 
     pub fn l_index_by_rev_rank(&self, bin: usize, rev_rank: usize) -> usize {
         let bin = &self.bins[bin];
-        self.indices[(bin.l_start + bin.l_count - 1) - rev_rank]
+        self.indices[(bin.l_start + bin.l_count - 1) - rev_rank].to_usize()
     }
 
     pub fn s_index_by_rank(&self, bin: usize, rank: usize) -> usize {
         let bin = &self.bins[bin];
-        self.indices[(bin.s_stop - bin.s_count) + rank]
+        self.indices[(bin.s_stop - bin.s_count) + rank].to_usize()
     }
 
     pub fn s_index_by_rev_rank(&self, bin: usize, rev_rank: usize) -> usize {
         let bin = &self.bins[bin];
-        self.indices[(bin.s_stop - 1) - rev_rank]
+        self.indices[(bin.s_stop - 1) - rev_rank].to_usize()
     }
 
-    pub fn iter_bins(&self) -> BothBucketIterator<'_, 'a, T, Bucket> {
+    pub fn iter_bins(&self) -> BothBucketIterator<'_, 'a, T, Bucket, I> {
         BothBucketIterator {
-            bucket: &self,
+            bucket: self,
             next_bin: 0,
             next_type: SuffixType::L,
         }
     }
 
-    pub fn iter_l_bins(&self) -> TypeLBucketIterator<'_, 'a, T, Bucket> {
+    pub fn iter_l_bins(&self) -> TypeLBucketIterator<'_, 'a, T, Bucket, I> {
         TypeLBucketIterator {
-            bucket: &self,
+            bucket: self,
             next_bin: 0,
         }
     }
 
-    pub fn iter_s_bins(&self) -> TypeSBucketIterator<'_, 'a, T, Bucket> {
+    pub fn iter_s_bins(&self) -> TypeSBucketIterator<'_, 'a, T, Bucket, I> {
         TypeSBucketIterator {
-            bucket: &self,
+            bucket: self,
             next_bin: 0,
         }
     }
@@ -159,11 +239,11 @@ impl<'a, T, Bucket: BucketOption<T>> SuffixArrayBucket<'a, T, Bucket> {
 
         match suffix_type {
             SuffixType::L => {
-                self.indices[bin.l_start + bin.l_count] = index;
+                self.indices[bin.l_start + bin.l_count] = I::from_usize(index);
                 bin.l_count += 1;
             }
             SuffixType::S => {
-                self.indices[(bin.s_stop - 1) - bin.s_count] = index;
+                self.indices[(bin.s_stop - 1) - bin.s_count] = I::from_usize(index);
                 bin.s_count += 1;
             }
         }
@@ -196,26 +276,28 @@ impl<'a, T, Bucket: BucketOption<T>> SuffixArrayBucket<'a, T, Bucket> {
     }
 }
 
-impl<'a, T, B: BucketOption<T>> IntoIterator for SuffixArrayBucket<'a, T, B> {
-    type Item = usize;
-    type IntoIter = std::vec::IntoIter<usize>;
+impl<'a, T, B: BucketOption<T>, I: SuffixIndex> IntoIterator for SuffixArrayBucket<'a, T, B, I> {
+    type Item = I;
+    type IntoIter = std::vec::IntoIter<I>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.indices.into_iter()
     }
 }
 
-pub struct BothBucketIterator<'b, 'a, T, B: BucketOption<T>> {
-    bucket: &'b SuffixArrayBucket<'a, T, B>,
+pub struct BothBucketIterator<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> {
+    bucket: &'b SuffixArrayBucket<'a, T, B, I>,
     next_bin: usize,
     next_type: SuffixType,
 }
 
-impl<'b, 'a, T, B: BucketOption<T>> Iterator for BothBucketIterator<'b, 'a, T, B> {
-    type Item = &'b [usize];
+impl<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> Iterator
+    for BothBucketIterator<'b, 'a, T, B, I>
+{
+    type Item = &'b [I];
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(bin) = self.bucket.bins.get(self.next_bin) else { return None };
+        let bin = self.bucket.bins.get(self.next_bin)?;
 
         Some(match self.next_type {
             SuffixType::L => {
@@ -233,16 +315,18 @@ impl<'b, 'a, T, B: BucketOption<T>> Iterator for BothBucketIterator<'b, 'a, T, B
     }
 }
 
-pub struct TypeLBucketIterator<'b, 'a, T, B: BucketOption<T>> {
-    bucket: &'b SuffixArrayBucket<'a, T, B>,
+pub struct TypeLBucketIterator<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> {
+    bucket: &'b SuffixArrayBucket<'a, T, B, I>,
     next_bin: usize,
 }
 
-impl<'b, 'a, T, B: BucketOption<T>> Iterator for TypeLBucketIterator<'b, 'a, T, B> {
-    type Item = &'b [usize];
+impl<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> Iterator
+    for TypeLBucketIterator<'b, 'a, T, B, I>
+{
+    type Item = &'b [I];
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(bin) = self.bucket.bins.get(self.next_bin) else { return None };
+        let bin = self.bucket.bins.get(self.next_bin)?;
 
         Some({
             let slice = &self.bucket.indices[bin.l_start..(bin.l_start + bin.l_count)];
@@ -252,16 +336,18 @@ impl<'b, 'a, T, B: BucketOption<T>> Iterator for TypeLBucketIterator<'b, 'a, T,
     }
 }
 
-pub struct TypeSBucketIterator<'b, 'a, T, B: BucketOption<T>> {
-    bucket: &'b SuffixArrayBucket<'a, T, B>,
+pub struct TypeSBucketIterator<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> {
+    bucket: &'b SuffixArrayBucket<'a, T, B, I>,
     next_bin: usize,
 }
 
-impl<'b, 'a, T, B: BucketOption<T>> Iterator for TypeSBucketIterator<'b, 'a, T, B> {
-    type Item = &'b [usize];
+impl<'b, 'a, T, B: BucketOption<T>, I: SuffixIndex> Iterator
+    for TypeSBucketIterator<'b, 'a, T, B, I>
+{
+    type Item = &'b [I];
 
     fn next(&mut self) -> Option<Self::Item> {
-        let Some(bin) = self.bucket.bins.get(self.next_bin) else { return None };
+        let bin = self.bucket.bins.get(self.next_bin)?;
 
         Some({
             let slice = &self.bucket.indices[(bin.s_stop - bin.s_count)..bin.s_stop];
@@ -277,13 +363,13 @@ pub enum SuffixType {
     L,
 }
 
-fn induced_sort<T, B: BucketOption<T>>(
+fn induced_sort<T, B: BucketOption<T>, I: SuffixIndex>(
     data: &[T],
     types: &[SuffixType],
-    bucket: &mut SuffixArrayBucket<T, B>,
+    bucket: &mut SuffixArrayBucket<T, B, I>,
 ) {
     // insert the last L-typed item
-    if data.len() > 0 {
+    if !data.is_empty() {
         let index = data.len() - 1;
 
         if let SuffixType::L = types[index] {
@@ -365,72 +451,118 @@ fn induced_sort<T, B: BucketOption<T>>(
     }
 }
 
-pub fn suffix_array<T, B: BucketOption<T>>(data: &[T], bucket_option: &B) -> Vec<usize> {
-    if data.is_empty() {
-        return vec![];
-    }
-
-    let mut types = vec![SuffixType::L; data.len()];
+// Buffers shared by every level of the construction. A level only needs its own string,
+// so the types, LMS positions and buckets of the next one overwrite them in place.
+struct Workspace<I: SuffixIndex> {
+    types: Vec<SuffixType>,
+    // names by half the index, as left-most S-typed indices are at least 2 apart
+    lms_halves: Vec<I>,
+    lms_indices: Vec<I>,
+    lms_names: Vec<I>,
+    indices: Vec<I>,
+    bins: Vec<BucketBin>,
+}
 
-    for index in (1..data.len()).rev() {
-        let bucket_index0 = bucket_option.bucket_index(&data[index - 1]);
-        let bucket_index1 = bucket_option.bucket_index(&data[index]);
-        types[index - 1] = if bucket_index0 == bucket_index1 {
-            types[index]
-        } else if bucket_index0 < bucket_index1 {
-            SuffixType::S
-        } else {
-            SuffixType::L
-        };
+impl<I: SuffixIndex> Workspace<I> {
+    fn new() -> Self {
+        Self {
+            types: vec![],
+            lms_halves: vec![],
+            lms_indices: vec![],
+            lms_names: vec![],
+            indices: vec![],
+            bins: vec![],
+        }
     }
 
-    let mut lms_orders: Vec<usize> = vec![0; data.len()];
-    let mut lms_ranges: Vec<(usize, usize)> = Vec::new();
+    // Classifies `data` into `types` and collects its left-most S-typed indices.
+    fn classify<T, B: BucketOption<T>>(&mut self, data: &[T], bucket_option: &B) {
+        self.types.clear();
+        self.types.resize(data.len(), SuffixType::L);
+
+        for index in (1..data.len()).rev() {
+            let bucket_index0 = bucket_option.bucket_index(&data[index - 1]);
+            let bucket_index1 = bucket_option.bucket_index(&data[index]);
+            self.types[index - 1] = if bucket_index0 == bucket_index1 {
+                self.types[index]
+            } else if bucket_index0 < bucket_index1 {
+                SuffixType::S
+            } else {
+                SuffixType::L
+            };
+        }
 
-    // collect left-most S-typed indices
-    for index in 1..data.len() {
-        let SuffixType::L = types[index - 1] else { continue };
-        let SuffixType::S = types[index] else { continue };
+        self.lms_indices.clear();
 
-        if let Some(lms_range) = lms_ranges.last_mut() {
-            lms_range.1 = index + 1;
+        for index in 1..data.len() {
+            if let (SuffixType::L, SuffixType::S) = (self.types[index - 1], self.types[index]) {
+                self.lms_indices.push(I::from_usize(index));
+            }
         }
-
-        lms_orders[index] = lms_ranges.len();
-        lms_ranges.push((index, data.len() + 1));
     }
 
-    let mut bucket = SuffixArrayBucket::new(data, &types, bucket_option);
+    // Names the LMS substrings of `data` into `lms_names`, in index order, so that the suffix
+    // array of the names orders the LMS suffixes. Returns the number of distinct names.
+    fn reduce<T, B: BucketOption<T>>(&mut self, data: &[T], bucket_option: &B) -> usize {
+        self.classify(data, bucket_option);
 
-    // insert left-most S-typed indices into S-typed buckets
-    for &(index, ..) in lms_ranges.iter() {
-        bucket.push(index, &data[index], SuffixType::S);
-    }
+        self.lms_names.clear();
+        if self.lms_indices.is_empty() {
+            return 0;
+        }
+
+        let mut bucket = SuffixArrayBucket::with_buffers(
+            data,
+            &self.types,
+            bucket_option,
+            std::mem::take(&mut self.indices),
+            std::mem::take(&mut self.bins),
+        );
+
+        // insert left-most S-typed indices into S-typed buckets
+        for &index in self.lms_indices.iter() {
+            let index = index.to_usize();
+            bucket.push(index, &data[index], SuffixType::S);
+        }
 
-    // Induced sort
-    induced_sort(data, &types, &mut bucket);
+        // Induced sort
+        induced_sort(data, &self.types, &mut bucket);
+
+        // an LMS substring runs up to and including the next left-most S-typed index
+        let types = &self.types;
+        let range_of = |start: usize| {
+            let stop = (start + 1..data.len())
+                .find(|&index| {
+                    matches!(
+                        (types[index - 1], types[index]),
+                        (SuffixType::L, SuffixType::S)
+                    )
+                })
+                .map_or(data.len() + 1, |index| index + 1);
+            (start, stop)
+        };
+
+        self.lms_halves.clear();
+        self.lms_halves.resize(data.len().div_ceil(2), I::default());
 
-    // Sort LMS
-    let lms_suffix_array = {
-        let mut lms_ranks: Vec<usize> = vec![0; lms_ranges.len()];
-        let mut lms_rank = 0usize;
-        let mut last_lms_order: Option<usize> = None;
+        let mut lms_name = 0usize;
+        let mut last_lms_range: Option<(usize, usize)> = None;
 
         // Scan buckets
         for bin in bucket.iter_s_bins() {
             for &index in bin {
-                if index <= 0 {
+                let index = index.to_usize();
+                if index == 0 {
                     continue;
                 }
 
-                let SuffixType::L = types[index - 1] else { continue };
-
-                let lms_order = lms_orders[index];
-                let lms_range = &lms_ranges[lms_order];
+                let SuffixType::L = types[index - 1] else {
+                    continue;
+                };
 
-                if let Some(last_order) = last_lms_order {
-                    let last_lms_range = lms_ranges[last_order];
+                let lms_range = range_of(index);
 
+                if let Some(last_lms_range) = last_lms_range {
                     let lms_len = lms_range.1 - lms_range.0;
                     let last_lms_len = last_lms_range.1 - last_lms_range.0;
 
@@ -453,82 +585,349 @@ pub fn suffix_array<T, B: BucketOption<T>>(data: &[T], bucket_option: &B) -> Vec
                     };
 
                     if !is_same {
-                        lms_rank += 1;
+                        lms_name += 1;
                     }
                 }
 
-                lms_ranks[lms_order] = lms_rank;
-                last_lms_order = Some(lms_order);
+                self.lms_halves[index / 2] = I::from_usize(lms_name);
+                last_lms_range = Some(lms_range);
             }
         }
 
-        // Calc LMS suffix array
-        suffix_array(
-            &lms_ranks,
-            &IndexBucket {
-                size: lms_ranks.len(),
-            },
-        )
-    };
+        self.lms_names.extend(
+            self.lms_indices
+                .iter()
+                .map(|&index| self.lms_halves[index.to_usize() / 2]),
+        );
 
-    // Clear all items from buckets
-    bucket.clear_all();
+        (self.indices, self.bins) = bucket.into_buffers();
 
-    // insert left-most S-typed indices into S-typed buckets in backward-backward order
-    for &suffix_index in lms_suffix_array.iter().rev() {
-        let (index, ..) = lms_ranges[suffix_index];
-        bucket.push(index, &data[index], SuffixType::S);
+        lms_name + 1
     }
 
-    // Induced sort
-    induced_sort(data, &types, &mut bucket);
+    // Sorts the suffixes of `data`, given the suffix array of its LMS names at the front of
+    // `indices`, leaving the suffix array of `data` in `indices`.
+    fn expand<T, B: BucketOption<T>>(&mut self, data: &[T], bucket_option: &B) {
+        self.classify(data, bucket_option);
 
-    // Flat buckets
-    bucket.into_iter().collect()
+        let lms_count = self.lms_indices.len();
+        for rank in 0..lms_count {
+            self.indices[rank] = self.lms_indices[self.indices[rank].to_usize()];
+        }
+        self.lms_indices.copy_from_slice(&self.indices[..lms_count]);
+
+        let mut bucket = SuffixArrayBucket::with_buffers(
+            data,
+            &self.types,
+            bucket_option,
+            std::mem::take(&mut self.indices),
+            std::mem::take(&mut self.bins),
+        );
+
+        // insert left-most S-typed indices into S-typed buckets in backward-backward order
+        for &index in self.lms_indices.iter().rev() {
+            let index = index.to_usize();
+            bucket.push(index, &data[index], SuffixType::S);
+        }
+
+        // Induced sort
+        induced_sort(data, &self.types, &mut bucket);
+
+        (self.indices, self.bins) = bucket.into_buffers();
+    }
 }
 
-pub fn rank_array(suffix_array: &[usize]) -> Vec<usize> {
-    let mut rank_array = vec![0usize; suffix_array.len()];
+// Panics when `data` has more than `I::MAX` items.
+pub fn suffix_array<T, B: BucketOption<T>, I: SuffixIndex>(
+    data: &[T],
+    bucket_option: &B,
+) -> Vec<I> {
+    if data.is_empty() {
+        return vec![];
+    }
+
+    assert!(
+        data.len() < I::MAX,
+        "{} items do not fit the suffix index",
+        data.len()
+    );
 
-    for (rank, &index) in suffix_array.iter().enumerate() {
-        rank_array[index] = rank;
+    #[cfg(feature = "parallel")]
+    if data.len() >= PARALLEL_THRESHOLD && rayon::current_num_threads() >= PARALLEL_THREADS {
+        return parallel_suffix_array(data, bucket_option);
+    }
+
+    let mut workspace = Workspace::new();
+
+    // LMS names of every level, each at most half as long as the one it was reduced from,
+    // with the start and name count of each
+    let mut strings: Vec<I> = Vec::new();
+    let mut levels: Vec<(usize, usize)> = Vec::new();
+
+    // Reduce until every LMS substring has a distinct name
+    let mut name_count = workspace.reduce(data, bucket_option);
+
+    while name_count < workspace.lms_names.len() {
+        let start = strings.len();
+        strings.extend_from_slice(&workspace.lms_names);
+        levels.push((start, name_count));
+
+        name_count = workspace.reduce(&strings[start..], &IndexBucket { size: name_count });
+    }
+
+    // Distinct names sort their suffixes by themselves
+    let lms_names = std::mem::take(&mut workspace.lms_names);
+    workspace.indices.resize(lms_names.len(), I::default());
+
+    for (lms_order, &lms_name) in lms_names.iter().enumerate() {
+        workspace.indices[lms_name.to_usize()] = I::from_usize(lms_order);
     }
 
+    // Expand back up to the input
+    while let Some((start, name_count)) = levels.pop() {
+        workspace.expand(&strings[start..], &IndexBucket { size: name_count });
+        strings.truncate(start);
+    }
+
+    workspace.expand(data, bucket_option);
+
+    // Flat buckets
+    workspace.indices
+}
+
+pub fn rank_array<I: SuffixIndex>(suffix_array: &[I]) -> Vec<I> {
+    let mut rank_array = vec![I::default(); suffix_array.len()];
+    fill_rank_array(suffix_array, &mut rank_array);
     rank_array
 }
 
-pub fn lcp_array<T: PartialEq + PartialOrd>(
+// Like `rank_array`, but overwriting an array of the same length, such as a spent PLCP array.
+pub fn fill_rank_array<I: SuffixIndex>(suffix_array: &[I], rank_array: &mut [I]) {
+    for (rank, &index) in suffix_array.iter().enumerate() {
+        rank_array[index.to_usize()] = I::from_usize(rank);
+    }
+}
+
+pub fn lcp_array<T: PartialEq + PartialOrd, I: SuffixIndex>(
     data: &[T],
-    suffix_array: &[usize],
-    rank_array: &[usize],
-) -> Vec<usize> {
+    suffix_array: &[I],
+    rank_array: &[I],
+) -> Vec<I> {
     if data.is_empty() {
         return vec![];
     }
 
-    let mut lcp_array = vec![0; data.len()];
+    let mut lcp_array = vec![I::default(); data.len()];
     let mut lcp = 0;
 
     for index0 in 0..data.len() {
-        let rank0 = rank_array[index0];
+        let rank0 = rank_array[index0].to_usize();
         let rank1 = if rank0 + 1 < data.len() {
             rank0 + 1
         } else {
             lcp = 0;
             continue;
         };
-        let index1 = suffix_array[rank1];
+        let index1 = suffix_array[rank1].to_usize();
 
         while index0 + lcp < data.len()
             && index1 + lcp < data.len()
-            && &data[index0 + lcp] == &data[index1 + lcp]
+            && data[index0 + lcp] == data[index1 + lcp]
         {
             lcp += 1;
         }
 
-        lcp_array[rank1] = lcp;
+        lcp_array[rank1] = I::from_usize(lcp);
         lcp = lcp.saturating_sub(1);
     }
 
     lcp_array
 }
+
+// Permuted LCP array, by text index rather than by rank, computed with the Φ algorithm:
+// the array first holds the index of the suffix ranked just before each one, and is
+// overwritten in place with the LCP against it, so no rank array is needed.
+pub fn plcp_array<T: PartialEq + PartialOrd, I: SuffixIndex>(
+    data: &[T],
+    suffix_array: &[I],
+) -> Vec<I> {
+    let mut plcp_array = vec![I::default(); data.len()];
+    let Some(&first) = suffix_array.first() else {
+        return plcp_array;
+    };
+
+    // the first suffix has none before it
+    plcp_array[first.to_usize()] = I::from_usize(data.len());
+    for ranks in suffix_array.windows(2) {
+        plcp_array[ranks[1].to_usize()] = ranks[0];
+    }
+
+    let mut lcp = 0;
+
+    for index0 in 0..data.len() {
+        let index1 = plcp_array[index0].to_usize();
+        if index1 == data.len() {
+            plcp_array[index0] = I::default();
+            lcp = 0;
+            continue;
+        }
+
+        while index0 + lcp < data.len()
+            && index1 + lcp < data.len()
+            && data[index0 + lcp] == data[index1 + lcp]
+        {
+            lcp += 1;
+        }
+
+        plcp_array[index0] = I::from_usize(lcp);
+        lcp = lcp.saturating_sub(1);
+    }
+
+    plcp_array
+}
+
+// Streams the LCP array, the same as `lcp_array`, out of the PLCP array.
+pub fn lcp_from_plcp<'a, I: SuffixIndex>(
+    suffix_array: &'a [I],
+    plcp_array: &'a [I],
+) -> impl Iterator<Item = I> + 'a {
+    suffix_array
+        .iter()
+        .map(|&index| plcp_array[index.to_usize()])
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    // xorshift64, seeded so that failures reproduce
+    pub struct Random(pub u64);
+
+    impl Random {
+        pub fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        // Up to `max_len` bytes from an alphabet of one letter to all bytes.
+        pub fn bytes(&mut self, max_len: usize) -> Vec<u8> {
+            let len = self.next() as usize % (max_len + 1);
+            let alphabet = [1, 2, 3, 4, 26, 256][self.next() as usize % 6];
+            (0..len).map(|_| (self.next() % alphabet) as u8).collect()
+        }
+    }
+
+    pub fn runs() -> Vec<Vec<u8>> {
+        [0, 1, 2, 3, 1000].map(|len| vec![0u8; len]).to_vec()
+    }
+
+    // Fibonacci strings up to `max_len` bytes, which reduce through the most levels.
+    pub fn fibonacci(max_len: usize) -> Vec<Vec<u8>> {
+        let mut strings = vec![vec![0u8], vec![0u8, 1]];
+        while strings.last().unwrap().len() < max_len {
+            let next = [&strings[strings.len() - 1][..], &strings[strings.len() - 2]].concat();
+            strings.push(next);
+        }
+        strings
+    }
+
+    pub fn naive_suffix_array<T: Ord>(data: &[T]) -> Vec<usize> {
+        let mut suffix_array: Vec<usize> = (0..data.len()).collect();
+        suffix_array.sort_by_key(|&index| &data[index..]);
+        suffix_array
+    }
+
+    fn check<I: SuffixIndex>(data: &[u8]) {
+        let suffix_array: Vec<I> = suffix_array(data, &U8Bucket);
+        let suffix_array: Vec<usize> = suffix_array.into_iter().map(I::to_usize).collect();
+        assert_eq!(suffix_array, naive_suffix_array(data), "{:?}", data);
+    }
+
+    #[test]
+    fn random_inputs() {
+        let mut random = Random(1);
+        for _ in 0..2000 {
+            check::<u32>(&random.bytes(300));
+        }
+        for _ in 0..10 {
+            check::<u32>(&random.bytes(20000));
+        }
+    }
+
+    #[test]
+    fn runs_of_one_byte() {
+        for data in runs() {
+            check::<u32>(&data);
+        }
+    }
+
+    #[test]
+    fn fibonacci_strings() {
+        for data in fibonacci(5000) {
+            check::<u32>(&data);
+        }
+    }
+
+    #[test]
+    fn u64_indices() {
+        let mut random = Random(2);
+        for _ in 0..200 {
+            check::<u64>(&random.bytes(300));
+        }
+        check::<u64>(fibonacci(5000).last().unwrap());
+    }
+
+    fn check_plcp(data: &[u8]) {
+        let suffix_array: Vec<u32> = suffix_array(data, &U8Bucket);
+        let lcp_array = lcp_array(data, &suffix_array, &rank_array(&suffix_array));
+        let plcp_array = plcp_array(data, &suffix_array);
+        assert!(
+            lcp_from_plcp(&suffix_array, &plcp_array).eq(lcp_array),
+            "{:?}",
+            data
+        );
+    }
+
+    #[test]
+    fn plcp_random_inputs() {
+        let mut random = Random(4);
+        for _ in 0..10000 {
+            check_plcp(&random.bytes(300));
+        }
+    }
+
+    #[test]
+    fn plcp_runs_of_one_byte() {
+        for data in runs() {
+            check_plcp(&data);
+        }
+    }
+
+    #[test]
+    fn plcp_fibonacci_strings() {
+        for data in fibonacci(100000) {
+            check_plcp(&data);
+        }
+    }
+
+    #[test]
+    fn index_alphabets() {
+        let mut random = Random(3);
+        for _ in 0..500 {
+            let size = 1 + random.next() as usize % 1000;
+            let len = random.next() as usize % 300;
+            let data: Vec<u32> = (0..len)
+                .map(|_| (random.next() as usize % size) as u32)
+                .collect();
+
+            let suffix_array: Vec<u32> = suffix_array(&data, &IndexBucket { size });
+            let suffix_array: Vec<usize> = suffix_array
+                .into_iter()
+                .map(|index| index as usize)
+                .collect();
+            assert_eq!(suffix_array, naive_suffix_array(&data), "{:?}", data);
+        }
+    }
+}
@@ -1,14 +1,93 @@
-use std::{fmt::Debug, marker::PhantomData};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    ops::Deref,
+    path::Path,
+    sync::Arc,
+};
 
-use super::suffix_array::{lcp_array, rank_array, suffix_array, BucketOption};
+use memmap2::Mmap;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
-pub struct SuffixReference<'a, T: PartialEq + PartialOrd> {
-    // data: &'a [T],
-    suffix_array: Vec<usize>,
-    rank_array: Vec<usize>,
-    // lcp_array: Vec<usize>,
-    back_array: Vec<Option<LcpBack>>,
-    phantom: PhantomData<&'a T>,
+use crate::error::{Error, Result};
+
+use super::{
+    algebra::{Associative, BinaryOperable, WithIdentity},
+    segment_tree::SegmentTree,
+    suffix_array::{
+        fill_rank_array, lcp_array, lcp_from_plcp, plcp_array, rank_array, suffix_array,
+        BucketOption, SuffixIndex,
+    },
+};
+
+// Shortest match a candidate is reported for.
+const MIN_CANDIDATE_LCP: usize = 2;
+
+// Saved index layout: magic, version, index size, byte order (0 little, 1 big), a reserved byte,
+// data size (u64), data XXH3 (u64) and XXH3 of the arrays (u64), all little-endian.
+// The suffix, rank, lcp and back arrays follow in native byte order, back entries being an
+// index then an lcp. The header size keeps every array aligned for mapping.
+pub const INDEX_MAGIC: [u8; 4] = *b"AZSX";
+pub const INDEX_VERSION: u8 = 1;
+const INDEX_HEADER_SIZE: usize = 32;
+const NATIVE_BYTE_ORDER: u8 = if cfg!(target_endian = "big") { 1 } else { 0 };
+
+// How `SuffixReference` builds its LCP array. Both give the same arrays.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LcpBuilder {
+    // Kasai, from the suffix and rank arrays.
+    Kasai,
+    // Φ, by way of the PLCP array, whose buffer then becomes the rank array. The LCP array is
+    // computed in text order rather than rank order, using as much memory as Kasai.
+    Phi,
+}
+
+pub struct SuffixReference<'a, T: PartialEq + PartialOrd, I: SuffixIndex> {
+    // the arrays were built from, which `save` checks it is given
+    data: &'a [T],
+    suffix_array: Array<I>,
+    rank_array: Array<I>,
+    lcp_array: Array<I>,
+    back_array: Array<CompactLcpBack<I>>,
+}
+
+// Array built in memory, or mapped from a saved index without copying.
+enum Array<E> {
+    Owned(Vec<E>),
+    Mapped {
+        map: Arc<Mmap>,
+        offset: usize,
+        len: usize,
+        phantom: PhantomData<E>,
+    },
+}
+
+impl<E> Array<E> {
+    fn mapped(map: &Arc<Mmap>, offset: usize, len: usize) -> Self {
+        Self::Mapped {
+            map: map.clone(),
+            offset,
+            len,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E> Deref for Array<E> {
+    type Target = [E];
+
+    fn deref(&self) -> &[E] {
+        match self {
+            Self::Owned(array) => array,
+            // only mapped with `SuffixIndex` integers or `CompactLcpBack`s of them, valid for
+            // any bytes, at offsets `load` checked to be in bounds and aligned to the index size
+            Self::Mapped {
+                map, offset, len, ..
+            } => unsafe { std::slice::from_raw_parts(map.as_ptr().add(*offset).cast(), *len) },
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -23,12 +102,39 @@ pub struct LcpBack {
     pub lcp: usize,
 }
 
-pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<LcpBack>> {
-    let mut back_array: Vec<Option<LcpBack>> = vec![None; suffix_array.len()];
+// `Option<LcpBack>` in two indices, an `lcp` of 0 standing for `None`,
+// since an occurrence sharing no prefix is no match either.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct CompactLcpBack<I: SuffixIndex> {
+    index: I,
+    lcp: I,
+}
+
+impl<I: SuffixIndex> CompactLcpBack<I> {
+    pub fn new(back: Option<LcpBack>) -> Self {
+        back.map_or(Default::default(), |back| Self {
+            index: I::from_usize(back.index),
+            lcp: I::from_usize(back.lcp),
+        })
+    }
+
+    pub fn get(&self) -> Option<LcpBack> {
+        let lcp = self.lcp.to_usize();
+
+        (lcp > 0).then(|| LcpBack {
+            index: self.index.to_usize(),
+            lcp,
+        })
+    }
+}
+
+pub fn back_array<I: SuffixIndex>(suffix_array: &[I], lcp_array: &[I]) -> Vec<CompactLcpBack<I>> {
+    let mut back_array: Vec<CompactLcpBack<I>> = vec![Default::default(); suffix_array.len()];
     let mut heads: Vec<LcpHead> = Vec::new();
 
-    for rank in 0..suffix_array.len() {
-        let index = suffix_array[rank];
+    for (rank, &index) in suffix_array.iter().enumerate() {
+        let index = index.to_usize();
         let mut acc_lcp = usize::MAX;
 
         while let Some(head) = heads.last_mut() {
@@ -38,17 +144,17 @@ pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<Lcp
                 break;
             }
 
-            back_array[head.index] = Some(LcpBack {
+            back_array[head.index] = CompactLcpBack::new(Some(LcpBack {
                 index,
                 lcp: head.lcp,
-            });
+            }));
 
             acc_lcp = head.lcp;
 
             heads.pop();
         }
 
-        let next_lcp = lcp_array.get(rank + 1).copied().unwrap_or(0);
+        let next_lcp = lcp_array.get(rank + 1).map_or(0, |lcp| lcp.to_usize());
         heads.push(LcpHead {
             index,
             lcp: next_lcp,
@@ -58,7 +164,7 @@ pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<Lcp
     heads.clear();
 
     for rank in (0..suffix_array.len()).rev() {
-        let index = suffix_array[rank];
+        let index = suffix_array[rank].to_usize();
         let mut acc_lcp = usize::MAX;
 
         while let Some(head) = heads.last_mut() {
@@ -68,13 +174,13 @@ pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<Lcp
                 break;
             }
 
-            if back_array[head.index].map_or(true, |back| {
+            if back_array[head.index].get().is_none_or(|back| {
                 (head.lcp > back.lcp) || ((head.lcp == back.lcp) && (index > back.index))
             }) {
-                back_array[head.index] = Some(LcpBack {
+                back_array[head.index] = CompactLcpBack::new(Some(LcpBack {
                     index,
                     lcp: head.lcp,
-                });
+                }));
             }
 
             acc_lcp = head.lcp;
@@ -82,7 +188,7 @@ pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<Lcp
             heads.pop();
         }
 
-        let next_lcp = lcp_array[rank];
+        let next_lcp = lcp_array[rank].to_usize();
         heads.push(LcpHead {
             index,
             lcp: next_lcp,
@@ -92,32 +198,537 @@ pub fn back_array(suffix_array: &[usize], lcp_array: &[usize]) -> Vec<Option<Lcp
     back_array
 }
 
-impl<'a, T: PartialEq + PartialOrd + Debug> SuffixReference<'a, T> {
+impl<'a, T: PartialEq + PartialOrd + Debug, I: SuffixIndex> SuffixReference<'a, T, I> {
     pub fn from_data(data: &'a [T], bucket_option: &impl BucketOption<T>) -> Self {
+        Self::from_data_with(data, bucket_option, LcpBuilder::Kasai)
+    }
+
+    pub fn from_data_with(
+        data: &'a [T],
+        bucket_option: &impl BucketOption<T>,
+        lcp_builder: LcpBuilder,
+    ) -> Self {
         let suffix_array = suffix_array(data, bucket_option);
-        let rank_array = rank_array(&suffix_array);
-        let lcp_array = lcp_array(data, &suffix_array, &rank_array);
+        let (rank_array, lcp_array) = match lcp_builder {
+            LcpBuilder::Kasai => {
+                let rank_array = rank_array(&suffix_array);
+                let lcp_array = lcp_array(data, &suffix_array, &rank_array);
+                (rank_array, lcp_array)
+            }
+            LcpBuilder::Phi => {
+                let mut plcp_array = plcp_array(data, &suffix_array);
+                let lcp_array = lcp_from_plcp(&suffix_array, &plcp_array).collect();
+                fill_rank_array(&suffix_array, &mut plcp_array);
+                (plcp_array, lcp_array)
+            }
+        };
         let back_array = back_array(&suffix_array, &lcp_array);
 
         Self {
-            // data,
-            suffix_array,
-            rank_array,
-            // lcp_array,
-            back_array,
-            phantom: PhantomData,
+            data,
+            suffix_array: Array::Owned(suffix_array),
+            rank_array: Array::Owned(rank_array),
+            lcp_array: Array::Owned(lcp_array),
+            back_array: Array::Owned(back_array),
         }
     }
 
     pub fn index(&self, rank: usize) -> usize {
-        self.suffix_array[rank]
+        self.suffix_array[rank].to_usize()
     }
 
     pub fn rank(&self, index: usize) -> usize {
-        self.rank_array[index]
+        self.rank_array[index].to_usize()
     }
 
     pub fn back(&self, index: usize) -> Option<LcpBack> {
-        self.back_array.get(index).copied().unwrap_or(None)
+        self.back_array.get(index).and_then(|back| back.get())
+    }
+
+    // Searches occurrences at most `window` positions back, see `WindowSearch`.
+    pub fn window_search(&self, window: usize) -> WindowSearch<'_, 'a, T, I> {
+        WindowSearch {
+            reference: self,
+            window,
+            trees: None,
+            next_index: 0,
+        }
+    }
+}
+
+// Finds earlier occurrences at most `window` positions back, for indices visited in
+// non-decreasing order. Positions enter a segment tree over ranks as the search passes them,
+// so the nearest passed rank inside the window, and how far a prefix is shared around a rank,
+// are found by descending trees instead of scanning ranks.
+pub struct WindowSearch<'r, 'a, T: PartialEq + PartialOrd, I: SuffixIndex> {
+    reference: &'r SuffixReference<'a, T, I>,
+    window: usize,
+    // built on first use, as `back` rarely needs them
+    trees: Option<WindowTrees<I>>,
+    next_index: usize,
+}
+
+struct WindowTrees<I: SuffixIndex> {
+    passed: SegmentTree<Passed<I>>,
+    lcps: SegmentTree<Lcp<I>>,
+}
+
+// One past the position at a rank, 0 until the search passes it, combined by maximum.
+#[derive(Clone, Copy)]
+struct Passed<I: SuffixIndex>(I);
+
+impl<I: SuffixIndex> BinaryOperable for Passed<I> {
+    fn operate(&self, other: &Self) -> Self {
+        Self(self.0.max(other.0))
+    }
+}
+
+impl<I: SuffixIndex> Associative for Passed<I> {}
+impl<I: SuffixIndex> WithIdentity for Passed<I> {
+    fn identity() -> Self {
+        Self(I::default())
+    }
+}
+
+// LCP array entries, combined by minimum.
+#[derive(Clone, Copy)]
+struct Lcp<I: SuffixIndex>(I);
+
+impl<I: SuffixIndex> BinaryOperable for Lcp<I> {
+    fn operate(&self, other: &Self) -> Self {
+        Self(self.0.min(other.0))
+    }
+}
+
+impl<I: SuffixIndex> Associative for Lcp<I> {}
+impl<I: SuffixIndex> WithIdentity for Lcp<I> {
+    fn identity() -> Self {
+        Self(I::from_usize(I::MAX))
+    }
+}
+
+impl<T: PartialEq + PartialOrd + Debug, I: SuffixIndex> WindowSearch<'_, '_, T, I> {
+    // Like `SuffixReference::back`, but only among occurrences inside the window.
+    pub fn back(&mut self, index: usize) -> Option<LcpBack> {
+        let back = self.reference.back(index)?;
+        if index - back.index <= self.window {
+            return Some(back);
+        }
+
+        self.nearest_longest(index, index.saturating_sub(self.window))
+    }
+
+    // Pareto frontier of occurrences inside the window: the nearest one for every achievable
+    // match length of at least `MIN_CANDIDATE_LCP`, ordered from the longest (and farthest)
+    // to the shortest. Only the first `limit` are searched for.
+    pub fn candidates(&mut self, index: usize, limit: usize) -> Vec<LcpBack> {
+        let mut candidates = vec![];
+        let mut start = index.saturating_sub(self.window);
+
+        // the nearest longest occurrence after each candidate shares a shorter prefix
+        while candidates.len() < limit {
+            let Some(back) = self.nearest_longest(index, start) else {
+                break;
+            };
+            if back.lcp < MIN_CANDIDATE_LCP {
+                break;
+            }
+
+            candidates.push(back);
+            start = back.index + 1;
+        }
+
+        candidates
+    }
+
+    // The nearest of the occurrences in `start..index` sharing the longest prefix with it.
+    fn nearest_longest(&mut self, index: usize, start: usize) -> Option<LcpBack> {
+        let reference = self.reference;
+        let len = reference.suffix_array.len();
+        if index >= len {
+            return None;
+        }
+
+        assert!(
+            index >= self.next_index,
+            "window search went back from {} to {}",
+            self.next_index,
+            index
+        );
+
+        let trees = self.trees.get_or_insert_with(|| WindowTrees {
+            passed: SegmentTree::with_len(len),
+            lcps: SegmentTree::from_values(reference.lcp_array.iter().map(|&lcp| Lcp(lcp))),
+        });
+
+        for passed in self.next_index..index {
+            trees
+                .passed
+                .set(reference.rank(passed), Passed(I::from_usize(passed + 1)));
+        }
+        self.next_index = index;
+
+        // every passed position is before `index`, so only the window start needs checking
+        let outside = |passed: &Passed<I>| passed.0.to_usize() <= start;
+        let rank = reference.rank(index);
+
+        let upper_lcp = match trees.passed.min_start(rank, outside) {
+            0 => 0,
+            stop => trees.lcps.get(stop..(rank + 1)).0.to_usize(),
+        };
+        let lower_lcp = match trees.passed.max_end(rank + 1, outside) {
+            stop if stop >= len => 0,
+            stop => trees.lcps.get((rank + 1)..(stop + 1)).0.to_usize(),
+        };
+
+        let lcp = upper_lcp.max(lower_lcp);
+        if lcp == 0 {
+            return None;
+        }
+
+        // the ranks sharing at least `lcp` with `index` hold the nearest such occurrence
+        let sharing = |shared: &Lcp<I>| shared.0.to_usize() >= lcp;
+        let upper = trees.lcps.min_start(rank + 1, sharing).saturating_sub(1);
+        let lower = trees.lcps.max_end(rank + 1, sharing);
+        let nearest = trees.passed.get(upper..lower).0.to_usize() - 1;
+
+        Some(LcpBack {
+            index: nearest,
+            lcp,
+        })
+    }
+}
+
+impl<'a, I: SuffixIndex> SuffixReference<'a, u8, I> {
+    // Writes the arrays built from `data` as an index `load` can map back.
+    pub fn save(&self, data: &[u8], path: &Path) -> Result<()> {
+        if self.data != data {
+            return Err(Error::IndexDataMismatch);
+        }
+
+        let mut header = [0u8; INDEX_HEADER_SIZE];
+        header[0..4].copy_from_slice(&INDEX_MAGIC);
+        header[4] = INDEX_VERSION;
+        header[5] = I::SIZE as u8;
+        header[6] = NATIVE_BYTE_ORDER;
+        header[8..16].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        header[16..24].copy_from_slice(&xxh3_64(data).to_le_bytes());
+
+        // the arrays are hashed as they are written, their hash filled in afterwards
+        let mut writer = HashWriter {
+            inner: BufWriter::new(File::create(path)?),
+            hasher: Xxh3::new(),
+        };
+        writer.inner.write_all(&header)?;
+
+        for array in [&self.suffix_array, &self.rank_array, &self.lcp_array] {
+            for &index in array.iter() {
+                index.write(&mut writer)?;
+            }
+        }
+        for back in self.back_array.iter() {
+            back.index.write(&mut writer)?;
+            back.lcp.write(&mut writer)?;
+        }
+
+        let mut file = writer.inner;
+        file.seek(SeekFrom::Start(24))?;
+        file.write_all(&writer.hasher.digest().to_le_bytes())?;
+        file.flush()?;
+        Ok(())
+    }
+
+    // Maps an index saved by `save`, checking it was built from `data` with the same index size
+    // and byte order. The arrays are only read as they are used.
+    pub fn load(data: &'a [u8], path: &Path) -> Result<Self> {
+        Self::map(data, path, false)
+    }
+
+    // Like `load`, also checking the arrays against their hash, which reads the whole file once.
+    pub fn load_verified(data: &'a [u8], path: &Path) -> Result<Self> {
+        Self::map(data, path, true)
+    }
+
+    fn map(data: &'a [u8], path: &Path, verify: bool) -> Result<Self> {
+        let file = File::open(path)?;
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        let header = map.get(..INDEX_HEADER_SIZE).ok_or(Error::Truncated)?;
+        if header[0..4] != INDEX_MAGIC {
+            return Err(Error::NotSuffixIndex);
+        }
+        if header[4] != INDEX_VERSION {
+            return Err(Error::UnsupportedIndexVersion(header[4]));
+        }
+        if header[5] as usize != I::SIZE || header[6] != NATIVE_BYTE_ORDER {
+            return Err(Error::IndexLayoutMismatch {
+                index_size: header[5] as usize,
+            });
+        }
+
+        let data_size = u64::from_le_bytes(header[8..16].try_into().unwrap()) as usize;
+        if data_size != data.len() || header[16..24] != xxh3_64(data).to_le_bytes() {
+            return Err(Error::IndexDataMismatch);
+        }
+
+        let expected = INDEX_HEADER_SIZE + data.len() * I::SIZE * 5;
+        if map.len() != expected {
+            return Err(Error::IndexSizeMismatch {
+                expected,
+                actual: map.len(),
+            });
+        }
+        if verify && header[24..32] != xxh3_64(&map[INDEX_HEADER_SIZE..]).to_le_bytes() {
+            return Err(Error::IndexCorrupted);
+        }
+
+        // arrays of `data.len()` indices each, the back array taking two indices per entry
+        let offset = |position: usize| INDEX_HEADER_SIZE + position * data.len() * I::SIZE;
+
+        Ok(Self {
+            data,
+            suffix_array: Array::mapped(&map, offset(0), data.len()),
+            rank_array: Array::mapped(&map, offset(1), data.len()),
+            lcp_array: Array::mapped(&map, offset(2), data.len()),
+            back_array: Array::mapped(&map, offset(3), data.len()),
+        })
+    }
+}
+
+// Writer hashing everything written through it.
+struct HashWriter<W: Write> {
+    inner: W,
+    hasher: Xxh3,
+}
+
+impl<W: Write> Write for HashWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let size = self.inner.write(buf)?;
+        self.hasher.update(&buf[..size]);
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::suffix_array::{
+        tests::{fibonacci, runs, Random},
+        U8Bucket,
+    };
+
+    fn check_builders(data: &[u8]) {
+        let kasai = SuffixReference::<_, u32>::from_data_with(data, &U8Bucket, LcpBuilder::Kasai);
+        let phi = SuffixReference::<_, u32>::from_data_with(data, &U8Bucket, LcpBuilder::Phi);
+
+        assert_eq!(&kasai.suffix_array[..], &phi.suffix_array[..], "{:?}", data);
+        assert_eq!(&kasai.rank_array[..], &phi.rank_array[..], "{:?}", data);
+        assert_eq!(&kasai.lcp_array[..], &phi.lcp_array[..], "{:?}", data);
+        for index in 0..data.len() {
+            assert_eq!(
+                kasai.back(index).map(|back| (back.index, back.lcp)),
+                phi.back(index).map(|back| (back.index, back.lcp)),
+                "{:?}",
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn phi_builds_as_kasai() {
+        let mut random = Random(1);
+        for _ in 0..1000 {
+            check_builders(&random.bytes(300));
+        }
+        for data in runs() {
+            check_builders(&data);
+        }
+        for data in fibonacci(20000) {
+            check_builders(&data);
+        }
+    }
+
+    const WINDOWS: [usize; 6] = [1, 2, 3, 16, 100, usize::MAX];
+
+    fn common_prefix(data: &[u8], index0: usize, index1: usize) -> usize {
+        data[index0..]
+            .iter()
+            .zip(data[index1..].iter())
+            .take_while(|(byte0, byte1)| byte0 == byte1)
+            .count()
+    }
+
+    // Nearest occurrence for every length, scanning back from `index`, the longest first.
+    fn brute_candidates(data: &[u8], index: usize, window: usize) -> Vec<(usize, usize)> {
+        let mut candidates = vec![];
+        let mut longest = 0;
+
+        for back_index in (index.saturating_sub(window)..index).rev() {
+            let lcp = common_prefix(data, back_index, index);
+            if lcp > longest {
+                longest = lcp;
+                candidates.push((back_index, lcp));
+            }
+        }
+
+        candidates.reverse();
+        candidates
+    }
+
+    fn check_window_search(data: &[u8]) {
+        let reference = SuffixReference::<_, u32>::from_data(data, &U8Bucket);
+
+        for window in WINDOWS {
+            let mut search = reference.window_search(window);
+
+            for index in 0..data.len() {
+                let expected = brute_candidates(data, index, window);
+
+                let back = search.back(index);
+                assert_eq!(
+                    back.map(|back| back.lcp),
+                    expected.first().map(|&(_, lcp)| lcp),
+                    "back of {} within {} in {:?}",
+                    index,
+                    window,
+                    data
+                );
+                if let Some(back) = back {
+                    assert!(back.index < index && index - back.index <= window);
+                    assert_eq!(common_prefix(data, back.index, index), back.lcp);
+                }
+
+                let candidates: Vec<(usize, usize)> = search
+                    .candidates(index, usize::MAX)
+                    .into_iter()
+                    .map(|back| (back.index, back.lcp))
+                    .collect();
+                let expected: Vec<(usize, usize)> = expected
+                    .into_iter()
+                    .filter(|&(_, lcp)| lcp >= MIN_CANDIDATE_LCP)
+                    .collect();
+                assert_eq!(
+                    candidates, expected,
+                    "candidates of {} within {} in {:?}",
+                    index, window, data
+                );
+
+                let limited: Vec<(usize, usize)> = search
+                    .candidates(index, 2)
+                    .into_iter()
+                    .map(|back| (back.index, back.lcp))
+                    .collect();
+                assert_eq!(limited, expected[..expected.len().min(2)]);
+            }
+        }
+    }
+
+    #[test]
+    fn window_search_random_inputs() {
+        let mut random = Random(2);
+        for _ in 0..300 {
+            check_window_search(&random.bytes(200));
+        }
+    }
+
+    #[test]
+    fn window_search_runs_of_one_byte() {
+        for data in runs() {
+            check_window_search(&data);
+        }
+    }
+
+    #[test]
+    fn window_search_fibonacci_strings() {
+        for data in fibonacci(1000) {
+            check_window_search(&data);
+        }
+    }
+
+    fn sample(seed: u64, len: usize) -> Vec<u8> {
+        let mut random = Random(seed);
+        (0..len).map(|_| (random.next() % 4) as u8).collect()
+    }
+
+    fn assert_same_arrays(loaded: &SuffixReference<u8, u32>, built: &SuffixReference<u8, u32>) {
+        assert_eq!(&loaded.suffix_array[..], &built.suffix_array[..]);
+        assert_eq!(&loaded.rank_array[..], &built.rank_array[..]);
+        assert_eq!(&loaded.lcp_array[..], &built.lcp_array[..]);
+        for index in 0..built.suffix_array.len() {
+            assert_eq!(
+                loaded.back(index).map(|back| (back.index, back.lcp)),
+                built.back(index).map(|back| (back.index, back.lcp))
+            );
+        }
+    }
+
+    #[test]
+    fn saved_index_loads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        let data = sample(3, 5000);
+        let reference = SuffixReference::<_, u32>::from_data(&data, &U8Bucket);
+        reference.save(&data, &path).unwrap();
+
+        assert_same_arrays(&SuffixReference::load(&data, &path).unwrap(), &reference);
+        assert_same_arrays(
+            &SuffixReference::load_verified(&data, &path).unwrap(),
+            &reference,
+        );
+
+        let mut other = data.clone();
+        other[0] ^= 1;
+        assert!(matches!(
+            SuffixReference::<_, u32>::load(&other, &path),
+            Err(Error::IndexDataMismatch)
+        ));
+        assert!(matches!(
+            SuffixReference::<_, u64>::load(&data, &path),
+            Err(Error::IndexLayoutMismatch { index_size: 4 })
+        ));
+    }
+
+    #[test]
+    fn only_verified_loads_check_the_arrays() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        let data = sample(4, 5000);
+        SuffixReference::<_, u32>::from_data(&data, &U8Bucket)
+            .save(&data, &path)
+            .unwrap();
+
+        let mut index = std::fs::read(&path).unwrap();
+        let last = index.len() - 1;
+        index[last] ^= 1;
+        std::fs::write(&path, &index).unwrap();
+
+        assert!(SuffixReference::<_, u32>::load(&data, &path).is_ok());
+        assert!(matches!(
+            SuffixReference::<_, u32>::load_verified(&data, &path),
+            Err(Error::IndexCorrupted)
+        ));
+    }
+
+    #[test]
+    fn save_rejects_other_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index");
+        let data = sample(5, 1000);
+        let reference = SuffixReference::<_, u32>::from_data(&data, &U8Bucket);
+
+        let mut other = data.clone();
+        other[data.len() / 2] ^= 1;
+        assert!(matches!(
+            reference.save(&other, &path),
+            Err(Error::IndexDataMismatch)
+        ));
+        assert!(matches!(
+            reference.save(&data[1..], &path),
+            Err(Error::IndexDataMismatch)
+        ));
     }
 }
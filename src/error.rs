@@ -0,0 +1,134 @@
+use std::fmt::Display;
+
+#[derive(Debug)]
+pub enum Error {
+    Io(std::io::Error),
+    Truncated,
+    InvalidHead,
+    OffsetBeyondWindow { left: usize, window: usize },
+    SizeMismatch { expected: usize, actual: usize },
+    NotFrozen,
+    UnsupportedVersion(u8),
+    UnknownCodec(u8),
+    UnknownChecksum(u8),
+    UnknownFlags(u8),
+    InvalidFilter { id: u8, parameter: usize },
+    BlockTooLarge { size: usize, limit: usize },
+    ChecksumMismatch { offset: Option<usize> },
+    InvalidCode,
+    InvalidPrimaryIndex { index: usize, size: usize },
+    InvalidIndexFile { size: usize, index_size: usize },
+    SuffixIndexOverflow { size: usize, index_size: usize },
+    NotSuffixIndex,
+    UnsupportedIndexVersion(u8),
+    IndexLayoutMismatch { index_size: usize },
+    IndexDataMismatch,
+    IndexCorrupted,
+    IndexSizeMismatch { expected: usize, actual: usize },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "{}", error),
+            Self::Truncated => write!(f, "unexpected end of stream"),
+            Self::InvalidHead => write!(f, "invalid length head"),
+            Self::OffsetBeyondWindow { left, window } => write!(
+                f,
+                "match offset {} reaches beyond the {} bytes extracted so far",
+                left, window
+            ),
+            Self::SizeMismatch { expected, actual } => write!(
+                f,
+                "size mismatch: expected {} bytes, extracted {} bytes",
+                expected, actual
+            ),
+            Self::NotFrozen => write!(f, "not a frozen stream (missing magic number)"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            Self::UnknownCodec(id) => write!(f, "unknown codec id {}", id),
+            Self::UnknownChecksum(id) => write!(f, "unknown checksum id {}", id),
+            Self::UnknownFlags(flags) => write!(f, "unknown header flags {:#04x}", flags),
+            Self::InvalidFilter { id, parameter } => {
+                write!(f, "unknown filter id {} with parameter {}", id, parameter)
+            }
+            Self::BlockTooLarge { size, limit } => {
+                write!(f, "block of {} bytes exceeds the limit of {}", size, limit)
+            }
+            Self::ChecksumMismatch {
+                offset: Some(offset),
+            } => {
+                write!(f, "checksum mismatch in block at offset {}", offset)
+            }
+            Self::ChecksumMismatch { offset: None } => write!(f, "checksum mismatch in stream"),
+            Self::InvalidCode => write!(f, "invalid entropy code"),
+            Self::InvalidPrimaryIndex { index, size } => write!(
+                f,
+                "primary index {} out of range for a block of {} bytes",
+                index, size
+            ),
+            Self::InvalidIndexFile { size, index_size } => write!(
+                f,
+                "index file of {} bytes is not a whole number of {}-byte indices",
+                size, index_size
+            ),
+            Self::SuffixIndexOverflow { size, index_size } => write!(
+                f,
+                "input of {} items or more does not fit {}-byte suffix indices",
+                size, index_size
+            ),
+            Self::NotSuffixIndex => write!(f, "not a suffix index (missing magic number)"),
+            Self::UnsupportedIndexVersion(version) => {
+                write!(f, "unsupported suffix index version {}", version)
+            }
+            Self::IndexLayoutMismatch { index_size } => write!(
+                f,
+                "suffix index of {}-byte indices built for another index size or byte order",
+                index_size
+            ),
+            Self::IndexDataMismatch => write!(f, "suffix index built from other data"),
+            Self::IndexCorrupted => write!(f, "suffix index arrays do not match their hash"),
+            Self::IndexSizeMismatch { expected, actual } => write!(
+                f,
+                "suffix index size mismatch: expected {} bytes, found {} bytes",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        // unwrap errors that passed through a `Read` or `Write` implementation
+        if error.get_ref().is_some_and(|inner| inner.is::<Self>()) {
+            return *error.into_inner().unwrap().downcast::<Self>().unwrap();
+        }
+
+        match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => Self::Truncated,
+            _ => Self::Io(error),
+        }
+    }
+}
+
+impl From<Error> for std::io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io(error) => error,
+            Error::Truncated => Self::new(std::io::ErrorKind::UnexpectedEof, error),
+            _ => Self::new(std::io::ErrorKind::InvalidData, error),
+        }
+    }
+}
@@ -1 +1,5 @@
 pub mod core;
+pub mod error;
+
+pub use crate::core::stream::{Decoder, Encoder};
+pub use error::{Error, Result};
@@ -4,6 +4,8 @@ use crate::commands::{
     Command, FreezeCommand, FreezeCommandArgs, MicrowaveCommand, MicrowaveCommandArgs,
 };
 
+use std::process::ExitCode;
+
 use clap::{Parser, Subcommand};
 use commands::{DumpCommand, DumpCommandArgs};
 
@@ -39,14 +41,19 @@ enum Commands {
     Dump(DumpCommandArgs),
 }
 
-fn main() -> std::io::Result<()> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    match &cli.command {
-        Commands::Freeze(args) => FreezeCommand.execute(args)?,
-        Commands::Microwave(args) => MicrowaveCommand.execute(args)?,
-        Commands::Dump(args) => DumpCommand.execute(args)?,
+    let result = match &cli.command {
+        Commands::Freeze(args) => FreezeCommand.execute(args),
+        Commands::Microwave(args) => MicrowaveCommand.execute(args),
+        Commands::Dump(args) => DumpCommand.execute(args),
+    };
+
+    if let Err(error) = result {
+        eprintln!("azuki: {}", error);
+        return ExitCode::FAILURE;
     }
 
-    Ok(())
+    ExitCode::SUCCESS
 }